@@ -0,0 +1,136 @@
+use crate::config::{Config, ConnectionProfile};
+
+/// 命令行用法说明
+pub const USAGE: &str = "用法: must [--profile <名称>] [--host <主机>] [--port <端口>]";
+
+/// telnet默认端口
+const DEFAULT_PORT: u16 = 23;
+
+/// 命令行参数，用于跳过连接选择界面直接连接服务器
+#[derive(Default)]
+pub struct CliArgs {
+    profile: Option<String>,
+    host: Option<String>,
+    port: Option<u16>,
+}
+
+impl CliArgs {
+    pub fn parse<I: Iterator<Item = String>>(mut args: I) -> Result<Self, String> {
+        let mut cli_args = Self::default();
+        while let Some(arg) = args.next() {
+            let mut value = || args.next().ok_or_else(|| format!("参数 {} 缺少取值", arg));
+            match arg.as_str() {
+                "--profile" => cli_args.profile = Some(value()?),
+                "--host" => cli_args.host = Some(value()?),
+                "--port" => {
+                    let port = value()?;
+                    let port = port.parse().map_err(|_| format!("无效的端口：{}", port))?;
+                    cli_args.port = Some(port);
+                }
+                _ => return Err(format!("未知参数：{}", arg)),
+            }
+        }
+        Ok(cli_args)
+    }
+
+    /// 根据命令行参数得到启动时直接连接的配置。
+    /// 返回None表示未指定，需展示连接选择界面
+    pub fn launch_profile(&self, config: &Config) -> Result<Option<ConnectionProfile>, String> {
+        let mut profile = match (&self.profile, &self.host) {
+            (Some(name), _) => config
+                .find_profile(name)
+                .cloned()
+                .ok_or_else(|| format!("配置文件中不存在名为 {} 的连接配置", name))?,
+            (None, Some(host)) => ConnectionProfile::new(
+                host.clone(),
+                host.clone(),
+                self.port.unwrap_or(DEFAULT_PORT),
+            ),
+            (None, None) if self.port.is_some() => {
+                return Err("--port 需要与 --host 或 --profile 一起使用".into())
+            }
+            (None, None) => return Ok(None),
+        };
+        // 命令行中的主机与端口优先于配置文件
+        if let Some(host) = &self.host {
+            profile.host = host.clone();
+        }
+        if let Some(port) = self.port {
+            profile.port = port;
+        }
+        Ok(Some(profile))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<CliArgs, String> {
+        CliArgs::parse(args.iter().map(|arg| arg.to_string()))
+    }
+
+    fn config() -> Config {
+        let mut profile = ConnectionProfile::new("测试".into(), "mud.example".into(), 5555);
+        profile.encoding = "gbk".into();
+        Config {
+            profiles: vec![profile],
+            ..Config::default()
+        }
+    }
+
+    #[test]
+    fn parses_arguments() {
+        let args = parse(&["--profile", "测试", "--host", "localhost", "--port", "4000"]).unwrap();
+        assert_eq!(args.profile.as_deref(), Some("测试"));
+        assert_eq!(args.host.as_deref(), Some("localhost"));
+        assert_eq!(args.port, Some(4000));
+        assert!(parse(&[])
+            .unwrap()
+            .launch_profile(&config())
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn rejects_invalid_arguments() {
+        assert_eq!(parse(&["--host"]).err().unwrap(), "参数 --host 缺少取值");
+        assert_eq!(parse(&["--port", "abc"]).err().unwrap(), "无效的端口：abc");
+        assert_eq!(
+            parse(&["--port", "70000"]).err().unwrap(),
+            "无效的端口：70000"
+        );
+        assert_eq!(parse(&["-x"]).err().unwrap(), "未知参数：-x");
+    }
+
+    #[test]
+    fn launch_profile_from_config_or_host() {
+        let config = config();
+        let profile = parse(&["--profile", "测试"])
+            .unwrap()
+            .launch_profile(&config);
+        let profile = profile.unwrap().unwrap();
+        assert_eq!(profile.address(), "mud.example:5555");
+        // 命令行中的端口覆盖配置文件，其余设置保持不变
+        let profile = parse(&["--profile", "测试", "--port", "23"]).unwrap();
+        let profile = profile.launch_profile(&config).unwrap().unwrap();
+        assert_eq!(profile.address(), "mud.example:23");
+        assert_eq!(profile.encoding, "gbk");
+        let profile = parse(&["--host", "localhost"]).unwrap();
+        let profile = profile.launch_profile(&config).unwrap().unwrap();
+        assert_eq!(profile.name, "localhost");
+        assert_eq!(profile.address(), "localhost:23");
+    }
+
+    #[test]
+    fn launch_profile_errors() {
+        let config = config();
+        let args = parse(&["--profile", "不存在"]).unwrap();
+        assert_eq!(
+            args.launch_profile(&config).err().unwrap(),
+            "配置文件中不存在名为 不存在 的连接配置"
+        );
+        let args = parse(&["--port", "4000"]).unwrap();
+        assert!(args.launch_profile(&config).is_err());
+    }
+}
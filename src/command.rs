@@ -0,0 +1,50 @@
+/// 客户端命令前缀，以此开头的输入由客户端处理，不会发送给服务器
+pub const COMMAND_PREFIX: char = '#';
+
+/// 由客户端自身处理的命令
+pub enum ClientCommand {
+    /// #connect [配置名称]：连接指定配置，未指定时重新连接当前配置
+    Connect(Option<String>),
+    /// #disconnect：主动断开当前连接，不会触发自动重连
+    Disconnect,
+    /// #oob [路径]：查看服务器通过GMCP、MSDP等协议发送的数据，如 #oob Char.Vitals
+    Oob(String),
+    /// #msdp <命令> [变量...]：发送MSDP命令，如 #msdp report HEALTH MANA、#msdp list COMMANDS
+    Msdp(String, Vec<String>),
+    /// #theme [主题名称]：切换所有会话的颜色主题，未指定时列出可用的主题
+    Theme(Option<String>),
+}
+
+impl ClientCommand {
+    /// 解析一行输入，若不是客户端命令则返回None
+    pub fn parse(input: &str) -> Option<Result<Self, String>> {
+        let input = input.trim().strip_prefix(COMMAND_PREFIX)?;
+        let (name, args) = input
+            .split_once(char::is_whitespace)
+            .map(|(name, args)| (name, args.trim()))
+            .unwrap_or((input, ""));
+        let command = match name {
+            "connect" if args.is_empty() => Ok(ClientCommand::Connect(None)),
+            "connect" => Ok(ClientCommand::Connect(Some(args.into()))),
+            "disconnect" => Ok(ClientCommand::Disconnect),
+            "oob" => Ok(ClientCommand::Oob(args.into())),
+            "theme" if args.is_empty() => Ok(ClientCommand::Theme(None)),
+            "theme" => Ok(ClientCommand::Theme(Some(args.into()))),
+            "msdp" => {
+                let mut words = args.split_whitespace().map(String::from);
+                match words.next() {
+                    Some(command) => Ok(ClientCommand::Msdp(
+                        command.to_ascii_uppercase(),
+                        words.collect(),
+                    )),
+                    None => Err(format!(
+                        "用法：{}msdp <list|report|unreport|send|reset> [变量...]",
+                        COMMAND_PREFIX
+                    )),
+                }
+            }
+            _ => Err(format!("未知的客户端命令：{}{}", COMMAND_PREFIX, name)),
+        };
+        Some(command)
+    }
+}
@@ -0,0 +1,145 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+pub use profile::ConnectionProfile;
+pub use proxy::{ProxyConfig, ProxyKind};
+pub use screen::{BellMode, ScreenConfig};
+pub use theme::Theme;
+
+mod profile;
+mod proxy;
+mod screen;
+mod theme;
+
+/// 客户端配置文件名，位于用户配置目录的must子目录下
+const CONFIG_FILE_NAME: &str = "config.toml";
+
+/// 客户端的持久化配置
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Config {
+    #[serde(default)]
+    pub screen: ScreenConfig,
+    #[serde(default)]
+    pub profiles: Vec<ConnectionProfile>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            screen: ScreenConfig::default(),
+            profiles: vec![ConnectionProfile::default()],
+        }
+    }
+}
+
+impl Config {
+    /// 配置目录，如Linux下为 ~/.config/must
+    pub fn dir() -> PathBuf {
+        dirs::config_dir()
+            .unwrap_or_else(|| PathBuf::from("."))
+            .join("must")
+    }
+
+    /// 配置文件路径，如Linux下为 ~/.config/must/config.toml
+    pub fn path() -> PathBuf {
+        Self::dir().join(CONFIG_FILE_NAME)
+    }
+
+    /// 加载配置文件。
+    /// 若配置文件不存在，则使用默认配置并写入磁盘，方便用户在此基础上修改；
+    /// 若配置文件内容有误，则使用默认配置，但不会覆盖用户的文件
+    pub fn load() -> Self {
+        Self::load_from(&Self::path())
+    }
+
+    fn load_from(path: &Path) -> Self {
+        match fs::read_to_string(path) {
+            Ok(content) => toml::from_str(&content).unwrap_or_else(|err| {
+                eprintln!("配置文件 {} 解析失败，使用默认配置：{}", path.display(), err);
+                Self::default()
+            }),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {
+                let config = Self::default();
+                if let Err(err) = config.save(path) {
+                    eprintln!("默认配置写入 {} 失败：{}", path.display(), err);
+                }
+                config
+            }
+            Err(err) => {
+                eprintln!("配置文件 {} 读取失败，使用默认配置：{}", path.display(), err);
+                Self::default()
+            }
+        }
+    }
+
+    fn save(&self, path: &Path) -> io::Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let content =
+            toml::to_string_pretty(self).map_err(io::Error::other)?;
+        fs::write(path, content)
+    }
+
+    pub fn find_profile(&self, name: &str) -> Option<&ConnectionProfile> {
+        self.profiles.iter().find(|p| p.name == name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 测试专用的临时配置文件路径，每个测试使用各自的目录
+    fn temp_path(test: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("must-test-{}-{}", std::process::id(), test));
+        let _ = fs::remove_dir_all(&dir);
+        dir.join(CONFIG_FILE_NAME)
+    }
+
+    #[test]
+    fn missing_file_writes_default_config() {
+        let path = temp_path("missing");
+        let config = Config::load_from(&path);
+        assert_eq!(config.profiles.len(), 1);
+        assert_eq!(config.profiles[0].name, "北大侠客行");
+        // 写入的默认配置可以原样读回
+        let saved = Config::load_from(&path);
+        assert_eq!(saved.profiles[0].address(), "pkuxkx.net:8081");
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn omitted_fields_use_defaults() {
+        let path = temp_path("partial");
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        let content = "[[profiles]]\nname = \"本地\"\nhost = \"localhost\"\nport = 4000\n";
+        fs::write(&path, content).unwrap();
+        let config = Config::load_from(&path);
+        let profile = config.find_profile("本地").unwrap();
+        assert_eq!(profile.address(), "localhost:4000");
+        assert_eq!(profile.encoding, "utf-8");
+        assert!(profile.mccp && profile.tls_verify && !profile.tls);
+        assert!(profile.proxy.is_none());
+        assert_eq!(
+            config.screen.scrollback_lines,
+            ScreenConfig::default().scrollback_lines
+        );
+        assert!(config.find_profile("北大侠客行").is_none());
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn malformed_file_is_not_overwritten() {
+        let path = temp_path("malformed");
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, "[[profiles]\nname = ").unwrap();
+        let config = Config::load_from(&path);
+        assert_eq!(config.profiles[0].name, "北大侠客行");
+        assert_eq!(fs::read_to_string(&path).unwrap(), "[[profiles]\nname = ");
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+}
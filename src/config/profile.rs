@@ -0,0 +1,109 @@
+use serde::{Deserialize, Serialize};
+
+use crate::config::ProxyConfig;
+
+/// 一个具名的MUD服务器连接配置
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ConnectionProfile {
+    /// 配置名称，用于在连接选择界面中展示以及命令行中通过 --profile 指定
+    pub name: String,
+    pub host: String,
+    pub port: u16,
+    /// 服务器所使用的文本编码，如 utf-8、gbk、big5
+    #[serde(default = "default_encoding")]
+    pub encoding: String,
+    /// 连接成功后是否自动发送角色名（以及密码）登录
+    #[serde(default)]
+    pub auto_login: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub character: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub password: Option<String>,
+    /// 连接意外断开后是否自动重连
+    #[serde(default)]
+    pub auto_reconnect: bool,
+    /// 启用GMCP后向服务器订阅的数据包，如 "Char 1"、"Room 1"
+    #[serde(default = "default_gmcp_packages")]
+    pub gmcp_packages: Vec<String>,
+    /// 启用MSDP后请求服务器主动上报的变量，如 "HEALTH"、"ROOM"
+    #[serde(default)]
+    pub msdp_report: Vec<String>,
+    /// 是否接受服务器使用MCCP2压缩发送的数据
+    #[serde(default = "default_mccp")]
+    pub mccp: bool,
+    /// 是否在服务器支持时使用MCCP3压缩发送的数据
+    #[serde(default)]
+    pub mccp3: bool,
+    /// 是否通过TLS加密连接
+    #[serde(default)]
+    pub tls: bool,
+    /// 是否校验服务器的TLS证书，使用自签名证书的服务器可以关闭校验
+    #[serde(default = "default_tls_verify")]
+    pub tls_verify: bool,
+    /// 经由代理服务器连接，不配置时直接连接
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub proxy: Option<ProxyConfig>,
+}
+
+impl ConnectionProfile {
+    pub fn new(name: String, host: String, port: u16) -> Self {
+        Self {
+            name,
+            host,
+            port,
+            encoding: default_encoding(),
+            auto_login: false,
+            character: None,
+            password: None,
+            auto_reconnect: false,
+            gmcp_packages: default_gmcp_packages(),
+            msdp_report: Vec::new(),
+            mccp: default_mccp(),
+            mccp3: false,
+            tls: false,
+            tls_verify: default_tls_verify(),
+            proxy: None,
+        }
+    }
+
+    /// 形如 host:port 的服务器地址，用于展示
+    pub fn address(&self) -> String {
+        format!("{}:{}", self.host, self.port)
+    }
+
+    /// 连接建立后需要依次发送的自动登录命令
+    pub fn auto_login_commands(&self) -> Vec<String> {
+        if !self.auto_login {
+            return Vec::new();
+        }
+        self.character
+            .iter()
+            .chain(self.password.iter())
+            .cloned()
+            .collect()
+    }
+}
+
+impl Default for ConnectionProfile {
+    fn default() -> Self {
+        Self::new("北大侠客行".into(), "pkuxkx.net".into(), 8081)
+    }
+}
+
+fn default_encoding() -> String {
+    "utf-8".into()
+}
+
+fn default_mccp() -> bool {
+    true
+}
+
+fn default_tls_verify() -> bool {
+    true
+}
+
+fn default_gmcp_packages() -> Vec<String> {
+    ["Char 1", "Char.Skills 1", "Char.Items 1", "Room 1", "Comm.Channel 1"]
+        .map(String::from)
+        .to_vec()
+}
@@ -0,0 +1,30 @@
+use serde::{Deserialize, Serialize};
+
+/// 代理服务器的类型
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ProxyKind {
+    Socks5,
+    /// 通过HTTP CONNECT方法建立隧道
+    Http,
+}
+
+/// 连接MUD服务器时经由的代理服务器
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ProxyConfig {
+    pub kind: ProxyKind,
+    pub host: String,
+    pub port: u16,
+    /// 代理服务器需要认证时的用户名与密码
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub username: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub password: Option<String>,
+}
+
+impl ProxyConfig {
+    /// 形如 host:port 的代理服务器地址，用于展示
+    pub fn address(&self) -> String {
+        format!("{}:{}", self.host, self.port)
+    }
+}
@@ -0,0 +1,70 @@
+use serde::{Deserialize, Serialize};
+
+use crate::config::theme::BUILTIN_THEMES;
+
+/// 终端屏幕的显示配置
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ScreenConfig {
+    /// 是否将最新的提示符固定显示在屏幕底部，而不是混在滚动的输出中
+    #[serde(default)]
+    pub fixed_prompt: bool,
+    /// 服务器发送响铃字符（BEL）时的提醒方式
+    #[serde(default)]
+    pub bell: BellMode,
+    /// 启动时使用的颜色主题，可以是内置主题或主题目录下的主题文件名
+    #[serde(default = "default_theme")]
+    pub theme: String,
+    /// 是否以亮色代替粗体字体显示粗体的8种标准颜色，与多数传统MUD客户端一致
+    #[serde(default)]
+    pub bold_is_bright: bool,
+    /// 回滚缓冲区最多保留的行数（不含屏幕），超出时丢弃最早的输出
+    #[serde(default = "default_scrollback_lines")]
+    pub scrollback_lines: usize,
+    /// 回滚查看时屏幕下方继续显示最新输出的区域所占的比例，为0时不分屏
+    #[serde(default = "default_scrollback_split")]
+    pub scrollback_split: f32,
+    /// 过长的行自动换行后，后续行缩进的字符数
+    #[serde(default)]
+    pub wrap_indent: usize,
+}
+
+impl Default for ScreenConfig {
+    fn default() -> Self {
+        Self {
+            fixed_prompt: false,
+            bell: BellMode::default(),
+            theme: default_theme(),
+            bold_is_bright: false,
+            scrollback_lines: default_scrollback_lines(),
+            scrollback_split: default_scrollback_split(),
+            wrap_indent: 0,
+        }
+    }
+}
+
+fn default_theme() -> String {
+    BUILTIN_THEMES[0].into()
+}
+
+fn default_scrollback_lines() -> usize {
+    10000
+}
+
+fn default_scrollback_split() -> f32 {
+    0.3
+}
+
+/// 响铃的提醒方式
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BellMode {
+    /// 忽略响铃
+    None,
+    /// 屏幕闪烁一下
+    #[default]
+    Visual,
+    /// 播放提示音
+    Sound,
+    /// 请求操作系统提醒用户（如任务栏图标闪烁），窗口已在前台时无效
+    Attention,
+}
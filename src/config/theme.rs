@@ -0,0 +1,302 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use ggez::graphics::Color;
+use serde::Deserialize;
+
+use crate::config::Config;
+
+/// 主题文件所在的目录名，位于配置文件同级，主题文件名为 <主题名称>.toml
+const THEME_DIR_NAME: &str = "themes";
+
+/// 内置主题的名称，默认使用第一个
+pub const BUILTIN_THEMES: [&str; 3] = ["xterm", "solarized", "zmud-classic"];
+
+/// 颜色主题，定义16种ANSI颜色、默认的前景与背景色以及输入框的颜色。
+/// 主题文件中未填写的项使用xterm主题的颜色
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct Theme {
+    /// 主题名称，即内置主题名或主题文件名
+    #[serde(skip)]
+    pub name: String,
+    /// 未设置颜色的文字的前景色
+    pub foreground: ThemeColor,
+    /// 终端屏幕的背景色
+    pub background: ThemeColor,
+    /// 输入框获得焦点时的文字与边框颜色
+    pub input_foreground: ThemeColor,
+    /// 输入框失去焦点时的文字与边框颜色
+    pub input_inactive: ThemeColor,
+    pub input_background: ThemeColor,
+    /// 依次为黑、红、绿、黄、蓝、品红、青、白，以及它们的亮色
+    pub ansi: [ThemeColor; 16],
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self::xterm()
+    }
+}
+
+impl Theme {
+    /// 按名称加载主题，优先使用主题目录下的同名文件，其次是内置主题
+    pub fn load(name: &str) -> Result<Self, String> {
+        Self::load_from(&Config::dir().join(THEME_DIR_NAME), name)
+    }
+
+    fn load_from(dir: &Path, name: &str) -> Result<Self, String> {
+        let path = dir.join(format!("{}.toml", name));
+        let mut theme = match fs::read_to_string(&path) {
+            Ok(content) => toml::from_str::<Theme>(&content)
+                .map_err(|err| format!("主题文件 {} 解析失败：{}", path.display(), err))?,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {
+                Self::builtin(name).ok_or_else(|| format!("不存在名为 {} 的主题", name))?
+            }
+            Err(err) => return Err(format!("主题文件 {} 读取失败：{}", path.display(), err)),
+        };
+        theme.name = name.into();
+        Ok(theme)
+    }
+
+    /// 所有可用的主题名称，包括内置主题与主题目录下的主题文件
+    pub fn available() -> Vec<String> {
+        let mut names: Vec<String> = BUILTIN_THEMES.iter().map(|name| name.to_string()).collect();
+        let entries = fs::read_dir(Config::dir().join(THEME_DIR_NAME));
+        for entry in entries.into_iter().flatten().flatten() {
+            let path = entry.path();
+            if path.extension().is_some_and(|ext| ext == "toml") {
+                if let Some(name) = path.file_stem().and_then(|stem| stem.to_str()) {
+                    if !names.iter().any(|n| n == name) {
+                        names.push(name.into());
+                    }
+                }
+            }
+        }
+        names
+    }
+
+    fn builtin(name: &str) -> Option<Self> {
+        match name {
+            "xterm" => Some(Self::xterm()),
+            "solarized" => Some(Self::solarized()),
+            "zmud-classic" => Some(Self::zmud_classic()),
+            _ => None,
+        }
+    }
+
+    /// xterm的默认配色
+    fn xterm() -> Self {
+        Self {
+            name: "xterm".into(),
+            foreground: ThemeColor([229, 229, 229]),
+            background: ThemeColor([0, 0, 0]),
+            input_foreground: ThemeColor([255, 255, 255]),
+            input_inactive: ThemeColor([100, 100, 100]),
+            input_background: ThemeColor([0, 0, 0]),
+            ansi: ThemeColor::palette([
+                [0, 0, 0],
+                [205, 0, 0],
+                [0, 205, 0],
+                [205, 205, 0],
+                [0, 0, 238],
+                [205, 0, 205],
+                [0, 205, 205],
+                [229, 229, 229],
+                [127, 127, 127],
+                [255, 0, 0],
+                [0, 255, 0],
+                [255, 255, 0],
+                [92, 92, 255],
+                [255, 0, 255],
+                [0, 255, 255],
+                [255, 255, 255],
+            ]),
+        }
+    }
+
+    /// Solarized的暗色配色
+    fn solarized() -> Self {
+        Self {
+            name: "solarized".into(),
+            foreground: ThemeColor([131, 148, 150]),
+            background: ThemeColor([0, 43, 54]),
+            input_foreground: ThemeColor([147, 161, 161]),
+            input_inactive: ThemeColor([88, 110, 117]),
+            input_background: ThemeColor([7, 54, 66]),
+            ansi: ThemeColor::palette([
+                [7, 54, 66],
+                [220, 50, 47],
+                [133, 153, 0],
+                [181, 137, 0],
+                [38, 139, 210],
+                [211, 54, 130],
+                [42, 161, 152],
+                [238, 232, 213],
+                [0, 43, 54],
+                [203, 75, 22],
+                [88, 110, 117],
+                [101, 123, 131],
+                [131, 148, 150],
+                [108, 113, 196],
+                [147, 161, 161],
+                [253, 246, 227],
+            ]),
+        }
+    }
+
+    /// zMUD等Windows下传统MUD客户端的配色
+    fn zmud_classic() -> Self {
+        Self {
+            name: "zmud-classic".into(),
+            foreground: ThemeColor([192, 192, 192]),
+            background: ThemeColor([0, 0, 0]),
+            input_foreground: ThemeColor([255, 255, 255]),
+            input_inactive: ThemeColor([128, 128, 128]),
+            input_background: ThemeColor([0, 0, 0]),
+            ansi: ThemeColor::palette([
+                [0, 0, 0],
+                [128, 0, 0],
+                [0, 128, 0],
+                [128, 128, 0],
+                [0, 0, 128],
+                [128, 0, 128],
+                [0, 128, 128],
+                [192, 192, 192],
+                [128, 128, 128],
+                [255, 0, 0],
+                [0, 255, 0],
+                [255, 255, 0],
+                [0, 0, 255],
+                [255, 0, 255],
+                [0, 255, 255],
+                [255, 255, 255],
+            ]),
+        }
+    }
+}
+
+/// 主题中的颜色，在主题文件中写作 "#rrggbb"
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub struct ThemeColor(pub [u8; 3]);
+
+impl ThemeColor {
+    fn palette(colors: [[u8; 3]; 16]) -> [ThemeColor; 16] {
+        colors.map(ThemeColor)
+    }
+}
+
+impl TryFrom<String> for ThemeColor {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        let invalid = || format!("颜色 {} 格式有误，应为 #rrggbb", value);
+        let hex = value.strip_prefix('#').ok_or_else(invalid)?;
+        if hex.len() != 6 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(invalid());
+        }
+        let mut rgb = [0u8; 3];
+        for (idx, component) in rgb.iter_mut().enumerate() {
+            *component =
+                u8::from_str_radix(&hex[idx * 2..idx * 2 + 2], 16).map_err(|_| invalid())?;
+        }
+        Ok(ThemeColor(rgb))
+    }
+}
+
+impl From<ThemeColor> for Color {
+    fn from(color: ThemeColor) -> Self {
+        let [r, g, b] = color.0;
+        Color::from_rgb(r, g, b)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+
+    /// 测试专用的临时主题目录，每个测试使用各自的目录
+    fn temp_dir(test: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("must-theme-{}-{}", std::process::id(), test));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn color(value: &str) -> Result<ThemeColor, String> {
+        ThemeColor::try_from(value.to_string())
+    }
+
+    #[test]
+    fn parses_colors() {
+        assert_eq!(color("#000000"), Ok(ThemeColor([0, 0, 0])));
+        assert_eq!(color("#1a2B3c"), Ok(ThemeColor([0x1a, 0x2b, 0x3c])));
+        assert_eq!(color("#FFFFFF"), Ok(ThemeColor([255, 255, 255])));
+        for value in [
+            "", "#", "123456", "#12345", "#1234567", "#12345g", "#+12345", "#ééé",
+        ] {
+            assert_eq!(
+                color(value),
+                Err(format!("颜色 {} 格式有误，应为 #rrggbb", value))
+            );
+        }
+    }
+
+    #[test]
+    fn builtin_themes_load_without_files() {
+        let dir = temp_dir("builtin");
+        for name in BUILTIN_THEMES {
+            assert_eq!(Theme::load_from(&dir, name).unwrap().name, name);
+        }
+        assert_eq!(
+            Theme::load_from(&dir, "solarized").unwrap().background,
+            ThemeColor([0, 43, 54])
+        );
+        assert_eq!(
+            Theme::load_from(&dir, "missing").unwrap_err(),
+            "不存在名为 missing 的主题"
+        );
+    }
+
+    #[test]
+    fn theme_file_overrides_builtin() {
+        let dir = temp_dir("override");
+        fs::write(dir.join("solarized.toml"), "background = \"#101010\"\n").unwrap();
+        let theme = Theme::load_from(&dir, "solarized").unwrap();
+        assert_eq!(theme.name, "solarized");
+        assert_eq!(theme.background, ThemeColor([16, 16, 16]));
+        // 同名的主题文件完全取代内置主题，未填写的项使用xterm的颜色
+        let xterm = Theme::xterm();
+        assert_eq!(theme.foreground, xterm.foreground);
+        assert_eq!(theme.ansi, xterm.ansi);
+    }
+
+    #[test]
+    fn partial_theme_file_falls_back_to_xterm() {
+        let dir = temp_dir("partial");
+        let content = "foreground = \"#c0c0c0\"\ninput_inactive = \"#808080\"\n";
+        fs::write(dir.join("custom.toml"), content).unwrap();
+        let theme = Theme::load_from(&dir, "custom").unwrap();
+        let xterm = Theme::xterm();
+        assert_eq!(theme.name, "custom");
+        assert_eq!(theme.foreground, ThemeColor([192, 192, 192]));
+        assert_eq!(theme.input_inactive, ThemeColor([128, 128, 128]));
+        assert_eq!(theme.background, xterm.background);
+        assert_eq!(theme.input_foreground, xterm.input_foreground);
+        assert_eq!(theme.input_background, xterm.input_background);
+        assert_eq!(theme.ansi, xterm.ansi);
+    }
+
+    #[test]
+    fn invalid_theme_file_is_an_error() {
+        let dir = temp_dir("invalid");
+        fs::write(dir.join("bad.toml"), "background = \"black\"\n").unwrap();
+        let err = Theme::load_from(&dir, "bad").unwrap_err();
+        assert!(err.starts_with("主题文件 "), "{}", err);
+        assert!(err.contains("颜色 black 格式有误"), "{}", err);
+    }
+}
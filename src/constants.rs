@@ -1,5 +1,7 @@
 /// 应用字体名称标识
 pub const FONT_FLAG_NAME: &str = "terminal_font";
+/// 粗体字体名称标识，粗体字体文件不存在时不会注册
+pub const FONT_BOLD_FLAG_NAME: &str = "terminal_font_bold";
 /// 界面上终端屏幕的字符显示高度
 pub const CHAR_CELL_HEIGHT: f32 = 28.;
 /// 界面上宽体字符的显示宽度
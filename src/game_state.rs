@@ -1,48 +1,215 @@
 use ggez::event::{EventHandler, MouseButton};
 use ggez::graphics::{Color, Rect};
 use ggez::input::keyboard::{KeyInput, KeyMods};
-use ggez::mint::Point2;
 use ggez::winit::event::VirtualKeyCode;
 use ggez::{graphics, Context, GameError, GameResult};
-use telnet::Telnet;
 
-use crate::screen::Screen;
-use crate::ui::text_input::TextInput;
+use crate::command::ClientCommand;
+use crate::config::{Config, ConnectionProfile, Theme};
+use crate::session::Session;
+use crate::ui::bell::Bell;
+use crate::ui::profile_picker::ProfilePicker;
+use crate::ui::tab_bar::{Tab, TabBar, TAB_BAR_HEIGHT};
 
 pub struct GameState {
-    telnet_client: Telnet,
-    screen: Screen,
-    text_input: TextInput,
+    config: Config,
+    /// 所有会话，至少有一个
+    sessions: Vec<Session>,
+    /// 当前标签对应的会话
+    active: usize,
+    /// 当前会话尚未选择连接配置时展示
+    profile_picker: ProfilePicker,
+    tab_bar: TabBar,
+    bell: Bell,
+    /// 所有会话共用的颜色主题
+    theme: Theme,
+    /// 是否注册了粗体字体
+    bold_font: bool,
+    /// 系统剪贴板，首次复制时创建。X11下剪贴板的内容由程序自身提供，需一直持有
+    clipboard: Option<arboard::Clipboard>,
+    screen_bounds: Rect,
+    input_bounds: Rect,
 }
 
 impl GameState {
-    pub fn new(_ctx: &mut Context) -> Self {
+    pub fn new(
+        _ctx: &mut Context,
+        config: Config,
+        launch_profile: Option<ConnectionProfile>,
+        bold_font: bool,
+    ) -> Self {
         let size = _ctx.gfx.window().inner_size();
-        let (screen_bounds, input_bounds) =
-            get_screen_and_input_bounds(size.width as f32, size.height as f32);
-        let mut telnet_client =
-            Telnet::connect(("pkuxkx.net", 8081), 1024).expect("Couldn't connect to the server...");
+        let (tab_bar_bounds, screen_bounds, input_bounds) =
+            get_layout_bounds(size.width as f32, size.height as f32);
+        let theme = Theme::load(&config.screen.theme).unwrap_or_else(|err| {
+            eprintln!("{}，使用默认主题", err);
+            Theme::default()
+        });
+        let mut session = Session::new(&config, bold_font, screen_bounds, input_bounds);
+        if let Some(profile) = launch_profile {
+            session.connect(profile);
+        }
         Self {
-            telnet_client,
-            screen: Screen::new(screen_bounds),
-            text_input: TextInput::new("hello, world.你好，世界。".into(), input_bounds),
+            profile_picker: ProfilePicker::new(config.profiles.clone(), screen_bounds),
+            bell: Bell::new(config.screen.bell),
+            theme,
+            bold_font,
+            clipboard: None,
+            config,
+            sessions: vec![session],
+            active: 0,
+            tab_bar: TabBar::new(tab_bar_bounds),
+            screen_bounds,
+            input_bounds,
+        }
+    }
+
+    fn active_session(&mut self) -> &mut Session {
+        &mut self.sessions[self.active]
+    }
+
+    /// 新建一个会话并切换过去，新会话展示连接选择界面
+    fn open_session(&mut self) {
+        let session = Session::new(
+            &self.config,
+            self.bold_font,
+            self.screen_bounds,
+            self.input_bounds,
+        );
+        self.sessions.push(session);
+        self.select_session(self.sessions.len() - 1);
+    }
+
+    /// 关闭当前会话，其连接随之断开。关闭最后一个会话时以一个新会话代替
+    fn close_session(&mut self) {
+        self.sessions.remove(self.active);
+        if self.sessions.is_empty() {
+            self.open_session();
+        } else {
+            self.select_session(self.active.min(self.sessions.len() - 1));
+        }
+    }
+
+    fn select_session(&mut self, idx: usize) {
+        if idx < self.sessions.len() {
+            self.active = idx;
+            self.active_session().clear_activity();
+        }
+    }
+
+    /// 在标签之间循环切换，offset为1切换到下一个，为-1切换到上一个
+    fn cycle_session(&mut self, offset: isize) {
+        let count = self.sessions.len() as isize;
+        let idx = (self.active as isize + offset).rem_euclid(count);
+        self.select_session(idx as usize);
+    }
+
+    /// 处理会话相关的快捷键，返回按键是否已被处理
+    fn session_key_down_event(&mut self, code: VirtualKeyCode, mods: KeyMods) -> bool {
+        if !mods.contains(KeyMods::CTRL) {
+            return false;
+        }
+        match code {
+            VirtualKeyCode::T => self.open_session(),
+            VirtualKeyCode::W => self.close_session(),
+            VirtualKeyCode::Tab if mods.contains(KeyMods::SHIFT) => self.cycle_session(-1),
+            VirtualKeyCode::Tab => self.cycle_session(1),
+            VirtualKeyCode::Key1 => self.select_session(0),
+            VirtualKeyCode::Key2 => self.select_session(1),
+            VirtualKeyCode::Key3 => self.select_session(2),
+            VirtualKeyCode::Key4 => self.select_session(3),
+            VirtualKeyCode::Key5 => self.select_session(4),
+            VirtualKeyCode::Key6 => self.select_session(5),
+            VirtualKeyCode::Key7 => self.select_session(6),
+            VirtualKeyCode::Key8 => self.select_session(7),
+            VirtualKeyCode::Key9 => self.select_session(8),
+            VirtualKeyCode::C => self.copy_selection(mods.contains(KeyMods::SHIFT)),
+            _ => return false,
+        }
+        true
+    }
+
+    /// 将当前会话屏幕上选中的文本复制到系统剪贴板，ansi为true时保留颜色等样式
+    fn copy_selection(&mut self, ansi: bool) {
+        let Some(text) = self.active_session().selected_text(ansi) else {
+            return;
+        };
+        let result = match self.clipboard.as_mut() {
+            Some(clipboard) => clipboard.set_text(text),
+            None => arboard::Clipboard::new().and_then(|clipboard| {
+                self.clipboard.insert(clipboard).set_text(text)
+            }),
+        };
+        if let Err(err) = result {
+            self.active_session()
+                .print_error(&format!("复制到剪贴板失败：{}", err));
+        }
+    }
+
+    /// 切换所有会话的颜色主题，未指定名称时列出可用的主题
+    fn switch_theme(&mut self, name: Option<String>) {
+        let Some(name) = name else {
+            let message = format!(
+                "当前主题：{}，可用主题：{}",
+                self.theme.name,
+                Theme::available().join("、")
+            );
+            self.active_session().print_info(&message);
+            return;
+        };
+        match Theme::load(&name) {
+            Ok(theme) => {
+                self.theme = theme;
+                self.active_session().print_info(&format!("已切换到主题 {}", name));
+            }
+            Err(err) => self.active_session().print_error(&err),
+        }
+    }
+
+    fn picker_key_down_event(&mut self, code: VirtualKeyCode) {
+        match code {
+            VirtualKeyCode::Up => self.profile_picker.select_prev(),
+            VirtualKeyCode::Down => self.profile_picker.select_next(),
+            VirtualKeyCode::Return | VirtualKeyCode::NumpadEnter => {
+                if let Some(profile) = self.profile_picker.selected_profile().cloned() {
+                    self.active_session().connect(profile);
+                }
+            }
+            _ => {}
         }
     }
 }
 
 impl EventHandler for GameState {
     fn update(&mut self, _ctx: &mut Context) -> GameResult {
-        let event = self.telnet_client.read_nonblocking().expect("Read error");
-        if let telnet::Event::Data(buffer) = event {
-            self.screen.load_buf(buffer);
+        // 后台会话同样需要处理连接事件，以免错过数据或自动重连
+        for (idx, session) in self.sessions.iter_mut().enumerate() {
+            session.update(idx != self.active);
+            if session.take_bell() {
+                self.bell.ring(_ctx);
+            }
         }
         Ok(())
     }
 
     fn draw(&mut self, ctx: &mut Context) -> GameResult {
-        let mut canvas = graphics::Canvas::from_frame(ctx, Color::BLACK);
-        self.screen.draw(&mut canvas, ctx);
-        self.text_input.draw(&mut canvas, ctx)?;
+        let mut canvas = graphics::Canvas::from_frame(ctx, Color::from(self.theme.background));
+        let tabs: Vec<Tab> = self
+            .sessions
+            .iter()
+            .map(|session| Tab {
+                title: session.title(),
+                activity: session.has_activity(),
+            })
+            .collect();
+        self.tab_bar.draw(&mut canvas, &tabs, self.active);
+        let session = &mut self.sessions[self.active];
+        if session.profile().is_none() {
+            self.profile_picker.draw(&mut canvas);
+            return canvas.finish(ctx);
+        }
+        session.draw(&mut canvas, ctx, &self.theme)?;
+        self.bell.draw(&mut canvas, self.screen_bounds);
         // Draw code here...
         canvas.finish(ctx)
     }
@@ -54,8 +221,50 @@ impl EventHandler for GameState {
         _x: f32,
         _y: f32,
     ) -> Result<(), GameError> {
-        self.text_input
-            .set_focused(self.text_input.bounds().contains(Point2::from([_x, _y])));
+        if let Some(idx) = self
+            .tab_bar
+            .tab_at(_x, _y, self.sessions.len(), self.active)
+        {
+            self.select_session(idx);
+            return Ok(());
+        }
+        if self.active_session().profile().is_none() {
+            self.profile_picker.select_at(_x, _y);
+            return Ok(());
+        }
+        self.active_session().mouse_button_down_event(_button, _x, _y);
+        Ok(())
+    }
+
+    fn mouse_button_up_event(
+        &mut self,
+        _ctx: &mut Context,
+        _button: MouseButton,
+        _x: f32,
+        _y: f32,
+    ) -> Result<(), GameError> {
+        self.active_session().mouse_button_up_event(_button);
+        Ok(())
+    }
+
+    fn mouse_motion_event(
+        &mut self,
+        _ctx: &mut Context,
+        _x: f32,
+        _y: f32,
+        _dx: f32,
+        _dy: f32,
+    ) -> Result<(), GameError> {
+        if self.active_session().profile().is_some() {
+            self.active_session().mouse_motion_event(_x, _y);
+        }
+        Ok(())
+    }
+
+    fn mouse_wheel_event(&mut self, _ctx: &mut Context, _x: f32, y: f32) -> GameResult {
+        if self.active_session().profile().is_some() {
+            self.active_session().mouse_wheel_event(y);
+        }
         Ok(())
     }
 
@@ -65,49 +274,29 @@ impl EventHandler for GameState {
         input: KeyInput,
         _repeated: bool,
     ) -> Result<(), GameError> {
-        if self.text_input.focused() {
-            match input {
-                KeyInput {
-                    keycode: Some(code),
-                    mods,
-                    ..
-                } => match code {
-                    VirtualKeyCode::Escape => self.text_input.set_focused(false),
-                    VirtualKeyCode::Delete | VirtualKeyCode::Back => {
-                        if KeyMods::CTRL == mods {
-                            self.text_input.delete_char(5)
-                        } else {
-                            self.text_input.delete_char(1)
-                        }
-                    }
-                    VirtualKeyCode::Return | VirtualKeyCode::Caret => {
-                        if let Some(mut txt_str) = self.text_input.commit() {
-                            // 文本在正常提交以后，还需要追加回车内容
-                            txt_str.push_str("\r\n");
-                            let bytes = txt_str.as_bytes();
-                            self.screen.load_buf(Box::from(bytes));
-                            let write_bytes = self.telnet_client
-                                .write(bytes)
-                                .expect("write data err.");
-                            println!("write_bytes: {}", write_bytes)
-                        }
-                    }
-                    _ => {}
-                },
-                _ => {}
+        if let Some(code) = input.keycode {
+            if self.session_key_down_event(code, input.mods) {
+                return Ok(());
             }
         }
-
+        if self.active_session().profile().is_none() {
+            if let Some(code) = input.keycode {
+                self.picker_key_down_event(code);
+            }
+            return Ok(());
+        }
+        if let Some(ClientCommand::Theme(name)) =
+            self.sessions[self.active].key_down_event(input, &self.config)
+        {
+            self.switch_theme(name);
+        }
         Ok(())
     }
 
     fn text_input_event(&mut self, _ctx: &mut Context, _character: char) -> Result<(), GameError> {
-        if self.text_input.focused() {
-            // 控制键在key_down_event中处理，这里仅处理提交内容
-            if _character.is_ascii_graphic() {
-                // 目前仅支持ascii中的可见字符
-                self.text_input.append_char(_character);
-            }
+        // 按住Ctrl时的字符属于快捷键，不输入到输入框中
+        if !_ctx.keyboard.active_mods().contains(KeyMods::CTRL) {
+            self.active_session().text_input_event(_character);
         }
         Ok(())
     }
@@ -118,21 +307,33 @@ impl EventHandler for GameState {
         width: f32,
         height: f32,
     ) -> Result<(), GameError> {
-        let (screen_bounds, input_bounds) = get_screen_and_input_bounds(width, height);
-        self.screen.update_bounds(screen_bounds);
-        self.text_input.update_bounds(input_bounds);
+        let (tab_bar_bounds, screen_bounds, input_bounds) = get_layout_bounds(width, height);
+        self.tab_bar.update_bounds(tab_bar_bounds);
+        self.profile_picker.update_bounds(screen_bounds);
+        for session in self.sessions.iter_mut() {
+            session.update_bounds(screen_bounds, input_bounds);
+        }
+        self.screen_bounds = screen_bounds;
+        self.input_bounds = input_bounds;
         Ok(())
     }
 }
 
-fn get_screen_and_input_bounds(window_width: f32, window_height: f32) -> (Rect, Rect) {
+/// 窗口自上而下依次为标签栏、终端屏幕与输入框
+fn get_layout_bounds(window_width: f32, window_height: f32) -> (Rect, Rect, Rect) {
     let input_height = 40f32;
-    let screen_bounds = Rect::from([0., 0., window_width, window_height - input_height]);
+    let tab_bar_bounds = Rect::from([0., 0., window_width, TAB_BAR_HEIGHT]);
+    let screen_bounds = Rect::from([
+        0.,
+        TAB_BAR_HEIGHT,
+        window_width,
+        window_height - TAB_BAR_HEIGHT - input_height,
+    ]);
     let input_bounds = Rect::from([
         0. + 15.,
-        screen_bounds.h,
+        screen_bounds.y + screen_bounds.h,
         window_width - 15. * 2.,
         input_height,
     ]);
-    (screen_bounds, input_bounds)
+    (tab_bar_bounds, screen_bounds, input_bounds)
 }
@@ -1,10 +1,18 @@
 use ggez::{ContextBuilder, event, graphics};
 use ggez::conf::{WindowMode, WindowSetup};
-use crate::constants::FONT_FLAG_NAME;
+use crate::cli::{CliArgs, USAGE};
+use crate::config::Config;
+use crate::constants::{FONT_BOLD_FLAG_NAME, FONT_FLAG_NAME};
 use crate::game_state::GameState;
 
+mod cli;
+mod command;
+mod config;
 mod game_state;
+mod net;
+mod oob;
 mod screen;
+mod session;
 mod constants;
 mod ui;
 mod utils;
@@ -13,6 +21,13 @@ mod utils;
 /// 且目录中有字体文件："DejaVuSansMono YaHei NF"
 /// 该字体文件请从项目目录/resources/目录下复制到对应目录
 fn main() {
+    let config = Config::load();
+    let launch_profile = CliArgs::parse(std::env::args().skip(1))
+        .and_then(|args| args.launch_profile(&config))
+        .unwrap_or_else(|err| {
+            eprintln!("{}\n{}", err, USAGE);
+            std::process::exit(2);
+        });
     // 建立游戏客户端
     let (mut ctx, event_loop) =
         ContextBuilder::new("must", "w4nzhen")
@@ -24,7 +39,11 @@ fn main() {
         FONT_FLAG_NAME,
         graphics::FontData::from_path(&ctx, "/DejaVuSansMono YaHei NF.ttf").expect("load font error"),
     );
-    let my_game = GameState::new(&mut ctx);
+    // 粗体字体是可选的，缺少时通过错位重复绘制来模拟粗体
+    let bold_font = graphics::FontData::from_path(&ctx, "/DejaVuSansMono YaHei NF Bold.ttf")
+        .map(|font| ctx.gfx.add_font(FONT_BOLD_FLAG_NAME, font))
+        .is_ok();
+    let my_game = GameState::new(&mut ctx, config, launch_profile, bold_font);
     // Run!
     event::run(ctx, event_loop, my_game);
 }
@@ -0,0 +1,191 @@
+use std::fmt;
+use std::str::FromStr;
+
+use encoding_rs::{CoderResult, Decoder, EncoderResult, Encoding, BIG5, GB18030, GBK, UTF_8};
+
+/// 服务器所使用的文本编码
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TextEncoding {
+    Utf8,
+    Gbk,
+    Gb18030,
+    Big5,
+    Latin1,
+}
+
+impl TextEncoding {
+    /// Latin-1的每个字节直接对应一个unicode码位，不需要借助encoding_rs
+    fn encoding(&self) -> Option<&'static Encoding> {
+        match self {
+            TextEncoding::Utf8 => Some(UTF_8),
+            TextEncoding::Gbk => Some(GBK),
+            TextEncoding::Gb18030 => Some(GB18030),
+            TextEncoding::Big5 => Some(BIG5),
+            TextEncoding::Latin1 => None,
+        }
+    }
+
+    /// 将待发送的文本编码为服务器编码下的字节，无法编码的字符以 ? 代替
+    pub fn encode(&self, text: &str) -> Vec<u8> {
+        let Some(encoding) = self.encoding() else {
+            return text
+                .chars()
+                .map(|c| u8::try_from(c).unwrap_or(b'?'))
+                .collect();
+        };
+        if encoding == UTF_8 {
+            return text.as_bytes().to_vec();
+        }
+        let mut encoder = encoding.new_encoder();
+        let mut bytes = Vec::with_capacity(text.len() * 2);
+        let mut src = text;
+        loop {
+            let (result, read) =
+                encoder.encode_from_utf8_to_vec_without_replacement(src, &mut bytes, true);
+            src = &src[read..];
+            match result {
+                EncoderResult::InputEmpty => break,
+                EncoderResult::OutputFull => bytes.reserve(src.len() * 2 + 4),
+                EncoderResult::Unmappable(_) => bytes.push(b'?'),
+            }
+        }
+        bytes
+    }
+}
+
+impl FromStr for TextEncoding {
+    type Err = String;
+
+    fn from_str(label: &str) -> Result<Self, Self::Err> {
+        match label.trim().to_ascii_lowercase().replace('_', "-").as_str() {
+            "utf-8" | "utf8" => Ok(TextEncoding::Utf8),
+            "gbk" | "gb2312" | "cp936" => Ok(TextEncoding::Gbk),
+            "gb18030" => Ok(TextEncoding::Gb18030),
+            "big5" | "big5-hkscs" => Ok(TextEncoding::Big5),
+            "latin1" | "latin-1" | "iso-8859-1" => Ok(TextEncoding::Latin1),
+            _ => Err(format!("不支持的编码：{}", label)),
+        }
+    }
+}
+
+impl fmt::Display for TextEncoding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let label = match self {
+            TextEncoding::Utf8 => "utf-8",
+            TextEncoding::Gbk => "gbk",
+            TextEncoding::Gb18030 => "gb18030",
+            TextEncoding::Big5 => "big5",
+            TextEncoding::Latin1 => "latin1",
+        };
+        f.write_str(label)
+    }
+}
+
+/// 流式解码器。
+/// 服务器数据是分块读取的，一个多字节字符可能被拆分在前后两次读取中，
+/// 解码器会保留不完整的字节，等待下一块数据到达后再继续解码
+pub struct TextDecoder {
+    decoder: Option<Decoder>,
+}
+
+impl TextDecoder {
+    pub fn new(encoding: TextEncoding) -> Self {
+        Self {
+            decoder: encoding.encoding().map(|e| e.new_decoder_without_bom_handling()),
+        }
+    }
+
+    pub fn decode(&mut self, buf: &[u8]) -> String {
+        let Some(decoder) = self.decoder.as_mut() else {
+            return buf.iter().map(|&b| char::from(b)).collect();
+        };
+        let capacity = decoder
+            .max_utf8_buffer_length(buf.len())
+            .unwrap_or(buf.len() * 3);
+        let mut text = String::with_capacity(capacity);
+        let mut src = buf;
+        loop {
+            // 只写入text已有的容量，写满后扩容继续解码剩余的字节
+            let (result, read, _) = decoder.decode_to_string(src, &mut text, false);
+            src = &src[read..];
+            match result {
+                CoderResult::InputEmpty => break,
+                CoderResult::OutputFull => text.reserve(src.len() * 3 + 4),
+            }
+        }
+        text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 在每个字节处把编码后的文本拆成两块分别解码，结果应与原文一致
+    fn assert_split_decodes(encoding: TextEncoding, text: &str) {
+        let bytes = encoding.encode(text);
+        for split in 0..=bytes.len() {
+            let mut decoder = TextDecoder::new(encoding);
+            let mut decoded = decoder.decode(&bytes[..split]);
+            decoded.push_str(&decoder.decode(&bytes[split..]));
+            assert_eq!(decoded, text, "{} split at {}", encoding, split);
+        }
+    }
+
+    #[test]
+    fn gbk_characters_split_across_chunks() {
+        assert_eq!(TextEncoding::Gbk.encode("中文"), [0xD6, 0xD0, 0xCE, 0xC4]);
+        let mut decoder = TextDecoder::new(TextEncoding::Gbk);
+        assert_eq!(decoder.decode(&[b'a', 0xD6]), "a");
+        assert_eq!(decoder.decode(&[0xD0, 0xCE]), "中");
+        assert_eq!(decoder.decode(&[0xC4, b'b']), "文b");
+        assert_split_decodes(TextEncoding::Gbk, "北大侠客行 pkuxkx");
+    }
+
+    #[test]
+    fn big5_characters_split_across_chunks() {
+        assert_eq!(TextEncoding::Big5.encode("中文"), [0xA4, 0xA4, 0xA4, 0xE5]);
+        assert_split_decodes(TextEncoding::Big5, "歡迎光臨，請輸入帳號");
+    }
+
+    #[test]
+    fn gb18030_four_byte_sequence_split_inside() {
+        // U+0080与表情符号在GB18030中均为四字节编码
+        assert_eq!(
+            TextEncoding::Gb18030.encode("\u{80}"),
+            [0x81, 0x30, 0x81, 0x30]
+        );
+        assert_eq!(TextEncoding::Gb18030.encode("😀").len(), 4);
+        let mut decoder = TextDecoder::new(TextEncoding::Gb18030);
+        assert_eq!(decoder.decode(&[0x81]), "");
+        assert_eq!(decoder.decode(&[0x30, 0x81]), "");
+        assert_eq!(decoder.decode(&[0x30]), "\u{80}");
+        assert_split_decodes(TextEncoding::Gb18030, "表情😀与中文");
+    }
+
+    #[test]
+    fn encode_round_trips() {
+        let text = "你好，world！";
+        for encoding in [
+            TextEncoding::Utf8,
+            TextEncoding::Gbk,
+            TextEncoding::Gb18030,
+            TextEncoding::Big5,
+        ] {
+            let mut decoder = TextDecoder::new(encoding);
+            assert_eq!(decoder.decode(&encoding.encode(text)), text, "{}", encoding);
+        }
+        let mut decoder = TextDecoder::new(TextEncoding::Latin1);
+        assert_eq!(decoder.decode(&TextEncoding::Latin1.encode("café")), "café");
+        // 无法编码的字符以 ? 代替
+        assert_eq!(TextEncoding::Latin1.encode("a中b"), b"a?b");
+        assert_eq!(TextEncoding::Big5.encode("a😀b"), b"a?b");
+    }
+
+    #[test]
+    fn long_input_is_not_truncated() {
+        let text = "中".repeat(10000);
+        let mut decoder = TextDecoder::new(TextEncoding::Gbk);
+        assert_eq!(decoder.decode(&TextEncoding::Gbk.encode(&text)), text);
+    }
+}
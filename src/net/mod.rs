@@ -0,0 +1,295 @@
+use std::io;
+use std::net::{TcpStream, ToSocketAddrs};
+use std::time::Duration;
+
+use telnet::{Event, Telnet, TelnetError, TelnetOption};
+
+use crate::config::ConnectionProfile;
+use crate::net::encoding::{TextDecoder, TextEncoding};
+use crate::net::options::eor::{IAC_EOR, IAC_GA};
+use crate::net::options::msdp::{self, MSDP};
+use crate::net::options::naws::WindowSize;
+use crate::net::options::{OptionOutput, TelnetCommand, TelnetOptions};
+use crate::net::stream::{EofAwareStream, FramedStream};
+use crate::net::tls::TlsStream;
+use crate::net::worker::ConnectionHandle;
+
+pub mod encoding;
+pub mod options;
+pub mod reconnect;
+mod proxy;
+mod stream;
+mod tls;
+pub mod worker;
+
+/// 建立TCP连接的超时时间
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+/// telnet库读取数据的缓冲区大小，需要能够容纳完整的GMCP等子协商数据
+const TELNET_BUFFER_SIZE: usize = 32 * 1024;
+/// 单次poll最多处理的telnet事件数，避免数据量过大时迟迟不能发送界面提交的命令
+const MAX_EVENTS_PER_POLL: usize = 256;
+
+/// 连接产生的、需要客户端处理的事件
+pub enum ConnectionEvent {
+    /// 连接已建立
+    Connected,
+    /// 连接建立失败，网络线程随之结束
+    Failed(String),
+    /// 已解码的服务器文本
+    Text(String),
+    /// telnet协议处理过程中出现的错误，连接本身仍然可用
+    Error(String),
+    /// 服务器通过 IAC GA 或 IAC EOR 标记了提示符的结束
+    Prompt,
+    /// 服务器开始（true）或停止（false）负责回显，开始时通常表示正在输入密码
+    ServerEcho(bool),
+    /// 通过GMCP收到的数据包名称及其数据
+    Gmcp(String, serde_json::Value),
+    /// 通过MSDP收到的变量名称及其取值
+    Msdp(String, serde_json::Value),
+    /// 服务器关闭了连接或读写失败，连接已不可用。总是连接的最后一个事件
+    Closed(String),
+}
+
+/// 连接状态
+pub enum ConnectionState {
+    /// 未连接，或连接已断开
+    Disconnected,
+    /// 网络线程正在建立连接
+    Connecting(ConnectionHandle),
+    Connected(ConnectionHandle),
+    /// 连接建立失败
+    Failed,
+}
+
+impl ConnectionState {
+    /// 正在建立或已经建立的连接，用于接收事件
+    pub fn handle(&self) -> Option<&ConnectionHandle> {
+        match self {
+            ConnectionState::Connecting(handle) | ConnectionState::Connected(handle) => {
+                Some(handle)
+            }
+            _ => None,
+        }
+    }
+
+    /// 已经建立的连接，用于发送数据
+    pub fn connection(&self) -> Option<&ConnectionHandle> {
+        match self {
+            ConnectionState::Connected(handle) => Some(handle),
+            _ => None,
+        }
+    }
+}
+
+/// 与MUD服务器之间的一条连接，负责在telnet之上完成文本的编解码。
+/// 连接的读写都是阻塞的，由[`worker`]中的网络线程持有
+pub struct Connection {
+    telnet: Telnet,
+    encoding: TextEncoding,
+    decoder: TextDecoder,
+    options: TelnetOptions,
+    /// 与telnet层共享的套接字，用于在发送数据前恢复阻塞模式
+    socket: TcpStream,
+}
+
+impl Connection {
+    pub fn open(profile: &ConnectionProfile) -> io::Result<Self> {
+        let encoding: TextEncoding = profile
+            .encoding
+            .parse()
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))?;
+        let stream = match &profile.proxy {
+            Some(proxy) => proxy::connect(proxy, &profile.host, profile.port)?,
+            None => connect_tcp(&profile.host, profile.port)?,
+        };
+        let socket = stream.try_clone()?;
+        let telnet = if profile.tls {
+            let stream = TlsStream::connect(stream, &profile.host, profile.tls_verify)?;
+            new_telnet(stream)
+        } else {
+            new_telnet(stream)
+        };
+        Ok(Self {
+            telnet,
+            encoding,
+            decoder: TextDecoder::new(encoding),
+            options: TelnetOptions::new(profile, encoding),
+            socket,
+        })
+    }
+
+    /// 最多等待wait时长，读取当前所有可用的telnet事件：
+    /// 数据按连接编码解码为文本，协商与子协商交由选项子系统处理并回应服务器。
+    /// 连接断开前已收到的数据仍会正常返回，随后以[`ConnectionEvent::Closed`]结束
+    pub fn poll(&mut self, wait: Duration) -> Vec<ConnectionEvent> {
+        let mut output = OptionOutput::default();
+        if let Err(err) = self.pump(wait, &mut output) {
+            output.emit(ConnectionEvent::Closed(err.to_string()));
+        }
+        output.take_events()
+    }
+
+    fn pump(&mut self, wait: Duration, output: &mut OptionOutput) -> io::Result<()> {
+        for idx in 0..MAX_EVENTS_PER_POLL {
+            // 只有第一次读取需要等待数据到达，之后只处理已经到达的数据
+            let event = if idx == 0 {
+                self.telnet.read_timeout(wait)?
+            } else {
+                self.telnet.read_nonblocking()?
+            };
+            match event {
+                Event::Data(buffer) => {
+                    output.emit(ConnectionEvent::Text(self.decoder.decode(&buffer)))
+                }
+                Event::Negotiation(action, option) => {
+                    self.options.negotiate(action, option, output)
+                }
+                Event::Subnegotiation(option, data) => {
+                    self.options.subnegotiate(option, &data, output)
+                }
+                Event::UnknownIAC(IAC_GA | IAC_EOR) => output.emit(ConnectionEvent::Prompt),
+                // 诸如NOP、AYT等未被telnet库识别的命令，MUD客户端无需处理
+                Event::UnknownIAC(_) => {}
+                Event::Error(err) => output.emit(ConnectionEvent::Error(format!(
+                    "telnet协议错误：{}",
+                    err
+                ))),
+                Event::NoData | Event::TimedOut => break,
+            }
+            self.send_commands(output.take_commands())?;
+        }
+        Ok(())
+    }
+
+    /// 更新终端窗口大小，在NAWS启用时告知服务器
+    pub fn set_window_size(&mut self, size: WindowSize) -> io::Result<()> {
+        let mut output = OptionOutput::default();
+        self.options.window_resized(size, &mut output);
+        self.send_commands(output.take_commands())
+    }
+
+    /// 发送MSDP命令，如 REPORT HEALTH。服务器未启用MSDP时返回false
+    pub fn send_msdp(&mut self, command: &str, values: &[String]) -> io::Result<bool> {
+        if !self.options.remote_enabled(MSDP) {
+            return Ok(false);
+        }
+        let data = msdp::encode_command(command, values);
+        self.send_commands(vec![TelnetCommand::Subnegotiate(
+            TelnetOption::parse(MSDP),
+            data,
+        )])?;
+        Ok(true)
+    }
+
+    fn send_commands(&mut self, commands: Vec<TelnetCommand>) -> io::Result<()> {
+        if !commands.is_empty() {
+            self.set_blocking()?;
+        }
+        for command in commands {
+            match command {
+                TelnetCommand::Negotiate(action, option) => {
+                    self.telnet.negotiate(&action, option)
+                }
+                TelnetCommand::Subnegotiate(option, data) => {
+                    self.telnet.subnegotiate(option, &escape_iac(&data))
+                }
+            }
+            .map_err(telnet_error)?;
+        }
+        Ok(())
+    }
+
+    /// 按连接编码发送一行命令，自动追加回车换行
+    pub fn send_line(&mut self, line: &str) -> io::Result<usize> {
+        let mut bytes = self.encoding.encode(line);
+        bytes.extend_from_slice(b"\r\n");
+        self.set_blocking()?;
+        self.telnet.write(&bytes)
+    }
+
+    /// telnet库的read_nonblocking会让套接字保持非阻塞模式，
+    /// 此时发送缓冲区已满会使写入以WouldBlock失败，因此发送前先恢复为阻塞模式
+    fn set_blocking(&self) -> io::Result<()> {
+        self.socket.set_nonblocking(false)
+    }
+}
+
+/// 在传输层之上建立telnet层，EOF检测与命令分帧（含MCCP）位于两者之间
+fn new_telnet<S: telnet::Stream + 'static>(stream: S) -> Telnet {
+    let stream = FramedStream::new(EofAwareStream::new(stream));
+    Telnet::from_stream(Box::new(stream), TELNET_BUFFER_SIZE)
+}
+
+/// telnet库发送子协商数据时不会转义其中的IAC，需要自行将其双写
+fn escape_iac(data: &[u8]) -> Vec<u8> {
+    let mut escaped = Vec::with_capacity(data.len());
+    for &byte in data {
+        escaped.push(byte);
+        if byte == 0xFF {
+            escaped.push(0xFF);
+        }
+    }
+    escaped
+}
+
+/// telnet库的写入错误不携带底层原因，统一视为连接断开
+fn telnet_error(err: TelnetError) -> io::Error {
+    io::Error::new(io::ErrorKind::BrokenPipe, err.to_string())
+}
+
+/// 依次尝试主机解析出的各个地址，直到连接成功
+fn connect_tcp(host: &str, port: u16) -> io::Result<TcpStream> {
+    let mut last_err = None;
+    for addr in (host, port).to_socket_addrs()? {
+        match TcpStream::connect_timeout(&addr, CONNECT_TIMEOUT) {
+            Ok(stream) => return Ok(stream),
+            Err(err) => last_err = Some(err),
+        }
+    }
+    Err(last_err.unwrap_or_else(|| {
+        io::Error::new(io::ErrorKind::NotFound, format!("无法解析主机：{}", host))
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+    use std::thread;
+
+    use super::*;
+
+    #[test]
+    fn send_after_nonblocking_read_waits_for_buffer_space() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            stream.write_all(b"hello\r\n").unwrap();
+            // 客户端写满发送缓冲区后才开始读取
+            thread::sleep(Duration::from_millis(200));
+            let mut received = Vec::new();
+            stream.read_to_end(&mut received).unwrap();
+            received.len()
+        });
+
+        let profile = ConnectionProfile::new("test".into(), "127.0.0.1".into(), port);
+        let mut connection = Connection::open(&profile).unwrap();
+        // 读完数据后telnet库以非阻塞模式读取，套接字停留在非阻塞模式
+        let mut text = String::new();
+        while !text.contains("hello") {
+            for event in connection.poll(Duration::from_secs(1)) {
+                match event {
+                    ConnectionEvent::Text(data) => text.push_str(&data),
+                    ConnectionEvent::Closed(reason) => panic!("{}", reason),
+                    _ => {}
+                }
+            }
+        }
+        let line = "x".repeat(32 * 1024 * 1024);
+        assert_eq!(connection.send_line(&line).unwrap(), line.len() + 2);
+        drop(connection);
+        assert_eq!(server.join().unwrap(), line.len() + 2);
+    }
+}
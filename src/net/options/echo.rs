@@ -0,0 +1,23 @@
+use telnet::TelnetOption;
+
+use crate::net::options::{OptionHandler, OptionOutput};
+use crate::net::ConnectionEvent;
+
+/// ECHO（RFC 857）。
+/// MUD服务器在要求输入密码时发送 WILL ECHO，表示由服务器负责回显（实际上不会回显），
+/// 客户端此时应隐藏输入内容并停止本地回显，直到服务器发送 WONT ECHO
+pub struct EchoOption;
+
+impl OptionHandler for EchoOption {
+    fn option(&self) -> u8 {
+        TelnetOption::Echo.as_byte()
+    }
+
+    fn accept_remote(&self) -> bool {
+        true
+    }
+
+    fn on_remote_changed(&mut self, enabled: bool, output: &mut OptionOutput) {
+        output.emit(ConnectionEvent::ServerEcho(enabled));
+    }
+}
@@ -0,0 +1,22 @@
+use telnet::TelnetOption;
+
+use crate::net::options::OptionHandler;
+
+/// IAC GA（Go Ahead），未协商SGA时服务器在提示符之后发送
+pub const IAC_GA: u8 = 249;
+/// IAC EOR（End of Record），协商EOR后服务器在提示符之后发送
+pub const IAC_EOR: u8 = 239;
+
+/// EOR（RFC 885）。MUD的提示符不以换行结尾，服务器通过 IAC EOR 或 IAC GA 标记提示符的结束，
+/// 这两个命令由[`crate::net::Connection`]转换为提示符事件
+pub struct EorOption;
+
+impl OptionHandler for EorOption {
+    fn option(&self) -> u8 {
+        TelnetOption::EOR.as_byte()
+    }
+
+    fn accept_remote(&self) -> bool {
+        true
+    }
+}
@@ -0,0 +1,177 @@
+use serde_json::{json, Value};
+
+use crate::net::options::{OptionHandler, OptionOutput};
+use crate::net::ConnectionEvent;
+
+/// GMCP的telnet选项编码
+pub const GMCP: u8 = 201;
+/// 只发送变化字段的数据包，其余数据包（如 Room.Info、Char.Items.List）每次都发送完整的数据
+const DELTA_PACKAGES: [&str; 2] = ["Char.Vitals", "Char.Status"];
+
+/// 数据包是否只包含变化的字段，此时应与原有数据合并而不是整体替换。数据包名称不区分大小写
+pub fn is_delta_package(package: &str) -> bool {
+    DELTA_PACKAGES
+        .iter()
+        .any(|delta| delta.eq_ignore_ascii_case(package))
+}
+
+/// GMCP（Generic MUD Communication Protocol），服务器通过子协商发送 "Package.Message <json>"，
+/// 如角色状态、房间信息、物品等，客户端将其解析后交由[`crate::oob::OobStore`]保存
+pub struct GmcpOption {
+    /// 启用后通过 Core.Supports.Set 告知服务器客户端需要的数据包，如 "Char 1"
+    supports: Vec<String>,
+}
+
+impl GmcpOption {
+    pub fn new(supports: Vec<String>) -> Self {
+        Self { supports }
+    }
+
+    fn send(&self, package: &str, data: Value, output: &mut OptionOutput) {
+        let message = format!("{} {}", package, data);
+        output.subnegotiate(GMCP, message.into_bytes());
+    }
+}
+
+impl OptionHandler for GmcpOption {
+    fn option(&self) -> u8 {
+        GMCP
+    }
+
+    fn accept_remote(&self) -> bool {
+        true
+    }
+
+    fn on_remote_changed(&mut self, enabled: bool, output: &mut OptionOutput) {
+        if !enabled {
+            return;
+        }
+        self.send(
+            "Core.Hello",
+            json!({ "client": "MUST", "version": env!("CARGO_PKG_VERSION") }),
+            output,
+        );
+        self.send("Core.Supports.Set", json!(self.supports), output);
+    }
+
+    fn on_subnegotiation(&mut self, data: &[u8], output: &mut OptionOutput) {
+        // GMCP规定使用UTF-8编码，与连接的文本编码无关
+        let message = String::from_utf8_lossy(data);
+        let (package, payload) = message
+            .split_once(char::is_whitespace)
+            .unwrap_or((&message, ""));
+        let payload = payload.trim();
+        let value = if payload.is_empty() {
+            Value::Null
+        } else {
+            match serde_json::from_str(payload) {
+                Ok(value) => value,
+                Err(err) => {
+                    output.emit(ConnectionEvent::Error(format!(
+                        "GMCP数据 {} 解析失败：{}",
+                        package, err
+                    )));
+                    return;
+                }
+            }
+        };
+        output.emit(ConnectionEvent::Gmcp(package.into(), value));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::net::options::TelnetCommand;
+
+    /// 收到一条GMCP消息，返回产生的事件，数据包以 (名称, 数据) 表示，错误以 Err 表示
+    fn receive(data: &[u8]) -> Vec<Result<(String, Value), String>> {
+        let mut output = OptionOutput::default();
+        GmcpOption::new(Vec::new()).on_subnegotiation(data, &mut output);
+        output
+            .take_events()
+            .into_iter()
+            .map(|event| match event {
+                ConnectionEvent::Gmcp(package, value) => Ok((package, value)),
+                ConnectionEvent::Error(message) => Err(message),
+                _ => panic!("unexpected event"),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn splits_package_and_payload() {
+        assert_eq!(
+            receive(br#"Char.Vitals {"hp": 100, "maxhp": 120}"#),
+            [Ok((
+                "Char.Vitals".into(),
+                json!({ "hp": 100, "maxhp": 120 })
+            ))]
+        );
+        // 包名与数据之间可以是任意空白，数据前后的空白被忽略
+        assert_eq!(
+            receive(b"Room.Info\n [\"north\", \"south\"] "),
+            [Ok(("Room.Info".into(), json!(["north", "south"])))]
+        );
+        assert_eq!(
+            receive("Comm.Channel \"你好\"".as_bytes()),
+            [Ok(("Comm.Channel".into(), json!("你好")))]
+        );
+    }
+
+    #[test]
+    fn message_without_payload_is_null() {
+        assert_eq!(
+            receive(b"Core.Ping"),
+            [Ok(("Core.Ping".into(), Value::Null))]
+        );
+        assert_eq!(
+            receive(b"Core.Ping  "),
+            [Ok(("Core.Ping".into(), Value::Null))]
+        );
+    }
+
+    #[test]
+    fn malformed_json_is_reported() {
+        let events = receive(br#"Char.Vitals {"hp": "#);
+        assert_eq!(events.len(), 1);
+        let message = events[0].as_ref().unwrap_err();
+        assert!(
+            message.starts_with("GMCP数据 Char.Vitals 解析失败"),
+            "{}",
+            message
+        );
+    }
+
+    #[test]
+    fn hello_and_supports_are_sent_when_enabled() {
+        let mut gmcp = GmcpOption::new(vec!["Char 1".into(), "Room 1".into()]);
+        let mut output = OptionOutput::default();
+        gmcp.on_remote_changed(false, &mut output);
+        assert!(output.take_commands().is_empty());
+        gmcp.on_remote_changed(true, &mut output);
+        let messages: Vec<String> = output
+            .take_commands()
+            .into_iter()
+            .map(|command| match command {
+                TelnetCommand::Subnegotiate(option, data) => {
+                    assert_eq!(option.as_byte(), GMCP);
+                    String::from_utf8(data).unwrap()
+                }
+                TelnetCommand::Negotiate(..) => panic!("unexpected negotiation"),
+            })
+            .collect();
+        assert_eq!(messages.len(), 2);
+        assert!(messages[0].starts_with("Core.Hello {"), "{}", messages[0]);
+        assert_eq!(messages[1], r#"Core.Supports.Set ["Char 1","Room 1"]"#);
+    }
+
+    #[test]
+    fn delta_packages() {
+        assert!(is_delta_package("Char.Vitals"));
+        assert!(is_delta_package("char.status"));
+        assert!(!is_delta_package("Room.Info"));
+        assert!(!is_delta_package("Char.Items.List"));
+        assert!(!is_delta_package("Char"));
+    }
+}
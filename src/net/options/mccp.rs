@@ -0,0 +1,56 @@
+use crate::net::options::{OptionHandler, OptionOutput};
+
+/// MCCP2的telnet选项编码，服务器向客户端发送的数据压缩
+pub const MCCP2: u8 = 86;
+/// MCCP3的telnet选项编码，客户端向服务器发送的数据压缩
+pub const MCCP3: u8 = 87;
+
+/// MCCP2（MUD Client Compression Protocol v2）。
+/// 压缩的开始与结束由[`crate::net::stream::FramedStream`]在telnet库之前透明处理，
+/// 这里只负责按配置接受或拒绝服务器的启用请求
+pub struct Mccp2Option {
+    accept: bool,
+}
+
+impl Mccp2Option {
+    pub fn new(accept: bool) -> Self {
+        Self { accept }
+    }
+}
+
+impl OptionHandler for Mccp2Option {
+    fn option(&self) -> u8 {
+        MCCP2
+    }
+
+    fn accept_remote(&self) -> bool {
+        self.accept
+    }
+}
+
+/// MCCP3，服务器同意后客户端发送 IAC SB MCCP3 IAC SE，此后发送的数据均被压缩
+pub struct Mccp3Option {
+    accept: bool,
+}
+
+impl Mccp3Option {
+    pub fn new(accept: bool) -> Self {
+        Self { accept }
+    }
+}
+
+impl OptionHandler for Mccp3Option {
+    fn option(&self) -> u8 {
+        MCCP3
+    }
+
+    fn accept_remote(&self) -> bool {
+        self.accept
+    }
+
+    fn on_remote_changed(&mut self, enabled: bool, output: &mut OptionOutput) {
+        if enabled {
+            output.subnegotiate(MCCP3, Vec::new());
+        }
+    }
+}
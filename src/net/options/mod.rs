@@ -0,0 +1,412 @@
+use std::collections::HashMap;
+
+use telnet::{Action, TelnetOption};
+
+use crate::config::ConnectionProfile;
+use crate::net::encoding::TextEncoding;
+use crate::net::options::echo::EchoOption;
+use crate::net::options::eor::EorOption;
+use crate::net::options::gmcp::GmcpOption;
+use crate::net::options::mccp::{Mccp2Option, Mccp3Option};
+use crate::net::options::msdp::MsdpOption;
+use crate::net::options::naws::{NawsOption, WindowSize};
+use crate::net::options::ttype::TtypeOption;
+use crate::net::ConnectionEvent;
+
+pub mod echo;
+pub mod eor;
+pub mod gmcp;
+pub mod mccp;
+pub mod msdp;
+pub mod naws;
+pub mod ttype;
+
+/// 一个telnet选项的处理器。
+/// 选项的启用与关闭由[`TelnetOptions`]按照RFC 1143的规则统一协商，
+/// 处理器只需声明是否接受该选项，并在选项状态变化或收到子协商数据时作出响应
+pub trait OptionHandler {
+    /// 处理的选项编码
+    fn option(&self) -> u8;
+
+    /// 服务器提出由它启用该选项（WILL）时，是否同意
+    fn accept_remote(&self) -> bool {
+        false
+    }
+
+    /// 服务器要求客户端启用该选项（DO）时，是否同意
+    fn accept_local(&self) -> bool {
+        false
+    }
+
+    /// 服务器一端的选项状态发生变化
+    fn on_remote_changed(&mut self, _enabled: bool, _output: &mut OptionOutput) {}
+
+    /// 客户端一端的选项状态发生变化
+    fn on_local_changed(&mut self, _enabled: bool, _output: &mut OptionOutput) {}
+
+    /// 收到该选项的子协商数据
+    fn on_subnegotiation(&mut self, _data: &[u8], _output: &mut OptionOutput) {}
+
+    /// 客户端的终端窗口大小发生变化
+    fn on_window_resized(&mut self, _size: WindowSize, _output: &mut OptionOutput) {}
+}
+
+/// 需要发送给服务器的telnet命令
+pub enum TelnetCommand {
+    Negotiate(Action, TelnetOption),
+    Subnegotiate(TelnetOption, Vec<u8>),
+}
+
+/// 选项处理过程中产生的输出，包括需要发送给服务器的命令以及需要交给客户端处理的事件
+#[derive(Default)]
+pub struct OptionOutput {
+    commands: Vec<TelnetCommand>,
+    events: Vec<ConnectionEvent>,
+}
+
+impl OptionOutput {
+    pub fn negotiate(&mut self, action: Action, option: u8) {
+        self.commands
+            .push(TelnetCommand::Negotiate(action, TelnetOption::parse(option)));
+    }
+
+    pub fn subnegotiate(&mut self, option: u8, data: Vec<u8>) {
+        self.commands
+            .push(TelnetCommand::Subnegotiate(TelnetOption::parse(option), data));
+    }
+
+    pub fn emit(&mut self, event: ConnectionEvent) {
+        self.events.push(event);
+    }
+
+    pub fn take_commands(&mut self) -> Vec<TelnetCommand> {
+        std::mem::take(&mut self.commands)
+    }
+
+    pub fn take_events(&mut self) -> Vec<ConnectionEvent> {
+        std::mem::take(&mut self.events)
+    }
+}
+
+/// 单个选项在客户端与服务器两端的启用状态
+#[derive(Default, Clone, Copy)]
+struct OptionState {
+    local: bool,
+    remote: bool,
+}
+
+/// telnet选项协商子系统，将协商与子协商分派给对应的选项处理器，
+/// 没有处理器的选项一律拒绝
+pub struct TelnetOptions {
+    handlers: Vec<Box<dyn OptionHandler>>,
+    states: HashMap<u8, OptionState>,
+}
+
+impl TelnetOptions {
+    pub fn new(profile: &ConnectionProfile, encoding: TextEncoding) -> Self {
+        Self {
+            handlers: vec![
+                Box::new(EchoOption),
+                Box::new(EorOption),
+                Box::new(NawsOption::new()),
+                Box::new(TtypeOption::new(encoding, profile.tls)),
+                Box::new(GmcpOption::new(profile.gmcp_packages.clone())),
+                Box::new(MsdpOption::new(profile.msdp_report.clone())),
+                Box::new(Mccp2Option::new(profile.mccp)),
+                Box::new(Mccp3Option::new(profile.mccp3)),
+            ],
+            states: HashMap::new(),
+        }
+    }
+
+    fn handler_mut(&mut self, option: u8) -> Option<&mut Box<dyn OptionHandler>> {
+        self.handlers.iter_mut().find(|h| h.option() == option)
+    }
+
+    /// 服务器一端是否已启用该选项
+    pub fn remote_enabled(&self, option: u8) -> bool {
+        self.states.get(&option).is_some_and(|s| s.remote)
+    }
+
+    /// 处理服务器发来的 WILL/WONT/DO/DONT。
+    /// 仅在状态确实发生变化时才回应，避免与服务器之间形成协商死循环
+    pub fn negotiate(&mut self, action: Action, option: TelnetOption, output: &mut OptionOutput) {
+        let option = option.as_byte();
+        let mut state = self.states.get(&option).copied().unwrap_or_default();
+        let handler = self.handlers.iter_mut().find(|h| h.option() == option);
+        match action {
+            Action::Will if !state.remote => {
+                if handler.as_ref().is_some_and(|h| h.accept_remote()) {
+                    state.remote = true;
+                    output.negotiate(Action::Do, option);
+                    if let Some(handler) = handler {
+                        handler.on_remote_changed(true, output);
+                    }
+                } else {
+                    output.negotiate(Action::Dont, option);
+                }
+            }
+            Action::Wont if state.remote => {
+                state.remote = false;
+                output.negotiate(Action::Dont, option);
+                if let Some(handler) = handler {
+                    handler.on_remote_changed(false, output);
+                }
+            }
+            Action::Do if !state.local => {
+                if handler.as_ref().is_some_and(|h| h.accept_local()) {
+                    state.local = true;
+                    output.negotiate(Action::Will, option);
+                    if let Some(handler) = handler {
+                        handler.on_local_changed(true, output);
+                    }
+                } else {
+                    output.negotiate(Action::Wont, option);
+                }
+            }
+            Action::Dont if state.local => {
+                state.local = false;
+                output.negotiate(Action::Wont, option);
+                if let Some(handler) = handler {
+                    handler.on_local_changed(false, output);
+                }
+            }
+            // 状态未发生变化，无需回应
+            _ => {}
+        }
+        self.states.insert(option, state);
+    }
+
+    /// 通知各处理器终端窗口大小发生了变化
+    pub fn window_resized(&mut self, size: WindowSize, output: &mut OptionOutput) {
+        for handler in self.handlers.iter_mut() {
+            handler.on_window_resized(size, output);
+        }
+    }
+
+    /// 将子协商数据交给对应的处理器，未启用的选项的子协商将被忽略
+    pub fn subnegotiate(&mut self, option: TelnetOption, data: &[u8], output: &mut OptionOutput) {
+        let option = option.as_byte();
+        let state = self.states.get(&option).copied().unwrap_or_default();
+        if !state.local && !state.remote {
+            return;
+        }
+        if let Some(handler) = self.handler_mut(option) {
+            handler.on_subnegotiation(data, output);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 测试用的选项，两端均接受启用，并以事件记录状态变化与子协商
+    struct TestOption;
+
+    const TEST_OPTION: u8 = 200;
+    /// 没有处理器的选项
+    const UNKNOWN_OPTION: u8 = 99;
+
+    impl OptionHandler for TestOption {
+        fn option(&self) -> u8 {
+            TEST_OPTION
+        }
+
+        fn accept_remote(&self) -> bool {
+            true
+        }
+
+        fn accept_local(&self) -> bool {
+            true
+        }
+
+        fn on_remote_changed(&mut self, enabled: bool, output: &mut OptionOutput) {
+            output.emit(ConnectionEvent::Text(format!("remote {}", enabled)));
+        }
+
+        fn on_local_changed(&mut self, enabled: bool, output: &mut OptionOutput) {
+            output.emit(ConnectionEvent::Text(format!("local {}", enabled)));
+        }
+
+        fn on_subnegotiation(&mut self, data: &[u8], output: &mut OptionOutput) {
+            output.emit(ConnectionEvent::Text(format!("sb {:?}", data)));
+        }
+    }
+
+    fn options() -> TelnetOptions {
+        TelnetOptions {
+            handlers: vec![Box::new(TestOption)],
+            states: HashMap::new(),
+        }
+    }
+
+    /// 收到一条协商后期望的回应与处理器事件
+    struct Step {
+        received: (Action, u8),
+        replies: &'static [(Action, u8)],
+        events: &'static [&'static str],
+        remote_enabled: bool,
+    }
+
+    fn replies(output: &mut OptionOutput) -> Vec<(u8, u8)> {
+        output
+            .take_commands()
+            .into_iter()
+            .map(|command| match command {
+                TelnetCommand::Negotiate(action, option) => (action.as_byte(), option.as_byte()),
+                TelnetCommand::Subnegotiate(..) => panic!("unexpected subnegotiation"),
+            })
+            .collect()
+    }
+
+    fn events(output: &mut OptionOutput) -> Vec<String> {
+        output
+            .take_events()
+            .into_iter()
+            .map(|event| match event {
+                ConnectionEvent::Text(text) => text,
+                _ => panic!("unexpected event"),
+            })
+            .collect()
+    }
+
+    /// telnet库中的Action没有实现Copy
+    fn copy_action(action: &Action) -> Action {
+        match action {
+            Action::Will => Action::Will,
+            Action::Wont => Action::Wont,
+            Action::Do => Action::Do,
+            Action::Dont => Action::Dont,
+        }
+    }
+
+    fn run(steps: &[Step]) {
+        let mut options = options();
+        for (idx, step) in steps.iter().enumerate() {
+            let (action, option) = &step.received;
+            let mut output = OptionOutput::default();
+            options.negotiate(
+                copy_action(action),
+                TelnetOption::parse(*option),
+                &mut output,
+            );
+            let expected: Vec<(u8, u8)> = step
+                .replies
+                .iter()
+                .map(|(action, option)| (action.as_byte(), *option))
+                .collect();
+            assert_eq!(replies(&mut output), expected, "step {}", idx);
+            assert_eq!(events(&mut output), step.events, "step {}", idx);
+            assert_eq!(
+                options.remote_enabled(*option),
+                step.remote_enabled,
+                "step {}",
+                idx
+            );
+        }
+    }
+
+    #[test]
+    fn redundant_remote_negotiation_is_not_answered() {
+        run(&[
+            Step {
+                received: (Action::Will, TEST_OPTION),
+                replies: &[(Action::Do, TEST_OPTION)],
+                events: &["remote true"],
+                remote_enabled: true,
+            },
+            Step {
+                received: (Action::Will, TEST_OPTION),
+                replies: &[],
+                events: &[],
+                remote_enabled: true,
+            },
+            Step {
+                received: (Action::Wont, TEST_OPTION),
+                replies: &[(Action::Dont, TEST_OPTION)],
+                events: &["remote false"],
+                remote_enabled: false,
+            },
+            Step {
+                received: (Action::Wont, TEST_OPTION),
+                replies: &[],
+                events: &[],
+                remote_enabled: false,
+            },
+        ]);
+    }
+
+    #[test]
+    fn redundant_local_negotiation_is_not_answered() {
+        run(&[
+            Step {
+                received: (Action::Do, TEST_OPTION),
+                replies: &[(Action::Will, TEST_OPTION)],
+                events: &["local true"],
+                remote_enabled: false,
+            },
+            Step {
+                received: (Action::Do, TEST_OPTION),
+                replies: &[],
+                events: &[],
+                remote_enabled: false,
+            },
+            Step {
+                received: (Action::Dont, TEST_OPTION),
+                replies: &[(Action::Wont, TEST_OPTION)],
+                events: &["local false"],
+                remote_enabled: false,
+            },
+            Step {
+                received: (Action::Dont, TEST_OPTION),
+                replies: &[],
+                events: &[],
+                remote_enabled: false,
+            },
+        ]);
+    }
+
+    #[test]
+    fn unknown_options_are_refused() {
+        run(&[
+            Step {
+                received: (Action::Will, UNKNOWN_OPTION),
+                replies: &[(Action::Dont, UNKNOWN_OPTION)],
+                events: &[],
+                remote_enabled: false,
+            },
+            Step {
+                received: (Action::Do, UNKNOWN_OPTION),
+                replies: &[(Action::Wont, UNKNOWN_OPTION)],
+                events: &[],
+                remote_enabled: false,
+            },
+            // 从未启用的选项被关闭时无需回应
+            Step {
+                received: (Action::Wont, UNKNOWN_OPTION),
+                replies: &[],
+                events: &[],
+                remote_enabled: false,
+            },
+            Step {
+                received: (Action::Dont, UNKNOWN_OPTION),
+                replies: &[],
+                events: &[],
+                remote_enabled: false,
+            },
+        ]);
+    }
+
+    #[test]
+    fn subnegotiation_requires_enabled_option() {
+        let mut options = options();
+        let mut output = OptionOutput::default();
+        options.subnegotiate(TelnetOption::parse(TEST_OPTION), &[1, 2], &mut output);
+        assert!(events(&mut output).is_empty());
+        options.negotiate(Action::Will, TelnetOption::parse(TEST_OPTION), &mut output);
+        output.take_events();
+        options.subnegotiate(TelnetOption::parse(TEST_OPTION), &[1, 2], &mut output);
+        assert_eq!(events(&mut output), ["sb [1, 2]"]);
+    }
+}
@@ -0,0 +1,309 @@
+use serde_json::{Map, Value};
+
+use crate::net::options::{OptionHandler, OptionOutput};
+use crate::net::ConnectionEvent;
+
+/// MSDP的telnet选项编码
+pub const MSDP: u8 = 69;
+
+const MSDP_VAR: u8 = 1;
+const MSDP_VAL: u8 = 2;
+const MSDP_TABLE_OPEN: u8 = 3;
+const MSDP_TABLE_CLOSE: u8 = 4;
+const MSDP_ARRAY_OPEN: u8 = 5;
+const MSDP_ARRAY_CLOSE: u8 = 6;
+
+/// 表与数组最多嵌套的层数，超过时视为格式错误，以免恶意数据导致递归解析时栈溢出
+const MAX_NESTING_DEPTH: usize = 32;
+
+/// MSDP（MUD Server Data Protocol），服务器通过子协商发送变量及其取值，
+/// 取值可以是字符串、表（TABLE）或数组（ARRAY），客户端将其解析为树形数据
+pub struct MsdpOption {
+    /// 启用后请求服务器在变化时主动上报的变量
+    report: Vec<String>,
+}
+
+impl MsdpOption {
+    pub fn new(report: Vec<String>) -> Self {
+        Self { report }
+    }
+}
+
+impl OptionHandler for MsdpOption {
+    fn option(&self) -> u8 {
+        MSDP
+    }
+
+    fn accept_remote(&self) -> bool {
+        true
+    }
+
+    fn on_remote_changed(&mut self, enabled: bool, output: &mut OptionOutput) {
+        if !enabled {
+            return;
+        }
+        // 获取可上报的变量列表，便于用户通过 #oob MSDP.REPORTABLE_VARIABLES 查看
+        output.subnegotiate(
+            MSDP,
+            encode_command("LIST", &["REPORTABLE_VARIABLES".into()]),
+        );
+        if !self.report.is_empty() {
+            output.subnegotiate(MSDP, encode_command("REPORT", &self.report));
+        }
+    }
+
+    fn on_subnegotiation(&mut self, data: &[u8], output: &mut OptionOutput) {
+        let mut parser = MsdpParser {
+            data,
+            pos: 0,
+            depth: 0,
+        };
+        while parser.peek().is_some() {
+            match parser.parse_variable() {
+                Some((name, value)) => output.emit(ConnectionEvent::Msdp(name, value)),
+                None => {
+                    output.emit(ConnectionEvent::Error("MSDP数据格式错误".into()));
+                    return;
+                }
+            }
+        }
+    }
+}
+
+/// 编码一条发送给服务器的MSDP命令，如 REPORT HEALTH MANA
+pub fn encode_command(command: &str, values: &[String]) -> Vec<u8> {
+    let mut data = vec![MSDP_VAR];
+    data.extend_from_slice(command.as_bytes());
+    for value in values {
+        data.push(MSDP_VAL);
+        data.extend_from_slice(value.as_bytes());
+    }
+    data
+}
+
+/// MSDP子协商数据的解析器
+struct MsdpParser<'a> {
+    data: &'a [u8],
+    pos: usize,
+    /// 当前所在的表与数组的嵌套层数
+    depth: usize,
+}
+
+impl MsdpParser<'_> {
+    fn peek(&self) -> Option<u8> {
+        self.data.get(self.pos).copied()
+    }
+
+    fn expect(&mut self, byte: u8) -> Option<()> {
+        (self.peek()? == byte).then(|| self.pos += 1)
+    }
+
+    /// 读取到下一个MSDP控制字节之前的字符串
+    fn parse_string(&mut self) -> String {
+        let start = self.pos;
+        while self
+            .peek()
+            .is_some_and(|b| !(MSDP_VAR..=MSDP_ARRAY_CLOSE).contains(&b))
+        {
+            self.pos += 1;
+        }
+        String::from_utf8_lossy(&self.data[start..self.pos]).into_owned()
+    }
+
+    /// VAR name VAL value [VAL value ...]，多个VAL视为数组
+    fn parse_variable(&mut self) -> Option<(String, Value)> {
+        self.expect(MSDP_VAR)?;
+        let name = self.parse_string();
+        let mut values = Vec::new();
+        while self.peek() == Some(MSDP_VAL) {
+            self.pos += 1;
+            values.push(self.parse_value()?);
+        }
+        let value = match values.len() {
+            0 => Value::Null,
+            1 => values.pop()?,
+            _ => Value::Array(values),
+        };
+        Some((name, value))
+    }
+
+    fn parse_value(&mut self) -> Option<Value> {
+        let nested = matches!(self.peek(), Some(MSDP_TABLE_OPEN | MSDP_ARRAY_OPEN));
+        if nested {
+            if self.depth >= MAX_NESTING_DEPTH {
+                return None;
+            }
+            self.depth += 1;
+        }
+        let value = self.parse_nested_or_string();
+        if nested {
+            self.depth -= 1;
+        }
+        value
+    }
+
+    fn parse_nested_or_string(&mut self) -> Option<Value> {
+        match self.peek() {
+            Some(MSDP_TABLE_OPEN) => {
+                self.pos += 1;
+                let mut table = Map::new();
+                while self.peek() != Some(MSDP_TABLE_CLOSE) {
+                    let (name, value) = self.parse_variable()?;
+                    table.insert(name, value);
+                }
+                self.pos += 1;
+                Some(Value::Object(table))
+            }
+            Some(MSDP_ARRAY_OPEN) => {
+                self.pos += 1;
+                let mut items = Vec::new();
+                while self.peek() != Some(MSDP_ARRAY_CLOSE) {
+                    self.expect(MSDP_VAL)?;
+                    items.push(self.parse_value()?);
+                }
+                self.pos += 1;
+                Some(Value::Array(items))
+            }
+            _ => Some(Value::String(self.parse_string())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    /// 解析一次子协商数据，变量以 (名称, 取值) 表示，格式错误以 Err 表示
+    fn parse(data: &[u8]) -> Vec<Result<(String, Value), String>> {
+        let mut output = OptionOutput::default();
+        MsdpOption::new(Vec::new()).on_subnegotiation(data, &mut output);
+        output
+            .take_events()
+            .into_iter()
+            .map(|event| match event {
+                ConnectionEvent::Msdp(name, value) => Ok((name, value)),
+                ConnectionEvent::Error(message) => Err(message),
+                _ => panic!("unexpected event"),
+            })
+            .collect()
+    }
+
+    /// 将可读的MSDP数据转换为字节，^、~、{、}、[、] 分别表示VAR、VAL以及其余的控制字节
+    fn msdp(text: &str) -> Vec<u8> {
+        text.bytes()
+            .map(|b| match b {
+                b'^' => MSDP_VAR,
+                b'~' => MSDP_VAL,
+                b'{' => MSDP_TABLE_OPEN,
+                b'}' => MSDP_TABLE_CLOSE,
+                b'[' => MSDP_ARRAY_OPEN,
+                b']' => MSDP_ARRAY_CLOSE,
+                b => b,
+            })
+            .collect()
+    }
+
+    fn var(name: &str, value: Value) -> Result<(String, Value), String> {
+        Ok((name.into(), value))
+    }
+
+    #[test]
+    fn variables_and_values() {
+        assert_eq!(parse(&msdp("^health~100")), [var("health", json!("100"))]);
+        assert_eq!(
+            parse(&msdp("^health~100^mana~50")),
+            [var("health", json!("100")), var("mana", json!("50"))]
+        );
+        // 没有取值的变量
+        assert_eq!(parse(&msdp("^health")), [var("health", Value::Null)]);
+        assert_eq!(parse(&msdp("^name~")), [var("name", json!(""))]);
+    }
+
+    #[test]
+    fn multiple_values_become_an_array() {
+        assert_eq!(
+            parse(&msdp("^REPORTABLE_VARIABLES~health~mana~room")),
+            [var(
+                "REPORTABLE_VARIABLES",
+                json!(["health", "mana", "room"])
+            )]
+        );
+    }
+
+    #[test]
+    fn nested_tables_and_arrays() {
+        assert_eq!(
+            parse(&msdp(
+                "^room~{^name~广场^exits~{^n~1^s~2}^items~[~剑~[~1~2]]}"
+            )),
+            [var(
+                "room",
+                json!({
+                    "name": "广场",
+                    "exits": { "n": "1", "s": "2" },
+                    "items": ["剑", ["1", "2"]]
+                })
+            )]
+        );
+        assert_eq!(parse(&msdp("^empty~{}")), [var("empty", json!({}))]);
+        assert_eq!(parse(&msdp("^empty~[]")), [var("empty", json!([]))]);
+    }
+
+    #[test]
+    fn malformed_input_is_reported() {
+        let error = || Err("MSDP数据格式错误".to_string());
+        // 表或数组没有结束
+        assert_eq!(parse(&msdp("^room~{^name~广场")), [error()]);
+        assert_eq!(parse(&msdp("^items~[~1~2")), [error()]);
+        // 数组元素缺少VAL
+        assert_eq!(parse(&msdp("^items~[1]")), [error()]);
+        // 开头不是VAR
+        assert_eq!(parse(&msdp("~value")), [error()]);
+        // 格式错误之前的变量照常解析
+        assert_eq!(
+            parse(&msdp("^hp~1^room~{")),
+            [var("hp", json!("1")), error()]
+        );
+    }
+
+    #[test]
+    fn deep_nesting_is_rejected() {
+        let within = format!(
+            "^deep~{}{}",
+            "[~".repeat(MAX_NESTING_DEPTH),
+            "]".repeat(MAX_NESTING_DEPTH)
+        );
+        assert!(parse(&msdp(&within))[0].is_ok());
+        let tables = format!(
+            "^deep~{}1{}",
+            "{^x~".repeat(MAX_NESTING_DEPTH),
+            "}".repeat(MAX_NESTING_DEPTH)
+        );
+        assert!(parse(&msdp(&tables))[0].is_ok());
+        // 除嵌套层数外格式完全正确的数据同样被拒绝
+        let rejected = [Err("MSDP数据格式错误".to_string())];
+        let beyond = |open: &str, close: &str, depth: usize| {
+            format!("^deep~{}1{}", open.repeat(depth), close.repeat(depth))
+        };
+        assert_eq!(
+            parse(&msdp(&beyond("[~", "]", MAX_NESTING_DEPTH + 1))),
+            rejected
+        );
+        assert_eq!(
+            parse(&msdp(&beyond("{^x~", "}", MAX_NESTING_DEPTH + 1))),
+            rejected
+        );
+        // 远超限制的嵌套不会导致栈溢出
+        assert_eq!(parse(&msdp(&beyond("[~", "]", 32 * 1024))), rejected);
+    }
+
+    #[test]
+    fn encodes_commands() {
+        assert_eq!(encode_command("LIST", &[]), msdp("^LIST"));
+        assert_eq!(
+            encode_command("REPORT", &["HEALTH".into(), "MANA".into()]),
+            msdp("^REPORT~HEALTH~MANA")
+        );
+    }
+}
@@ -0,0 +1,60 @@
+use telnet::TelnetOption;
+
+use crate::net::options::{OptionHandler, OptionOutput};
+
+/// 客户端终端窗口的大小，以字符为单位
+#[derive(Clone, Copy, PartialEq, Eq, Default)]
+pub struct WindowSize {
+    pub columns: u16,
+    pub rows: u16,
+}
+
+/// NAWS（RFC 1073），将客户端的窗口大小告知服务器，以便服务器按照窗口宽度折行
+pub struct NawsOption {
+    enabled: bool,
+    size: WindowSize,
+    /// 最近一次发送给服务器的大小，避免窗口拖动时重复发送相同的大小
+    sent_size: Option<WindowSize>,
+}
+
+impl NawsOption {
+    pub fn new() -> Self {
+        Self {
+            enabled: false,
+            size: WindowSize::default(),
+            sent_size: None,
+        }
+    }
+
+    fn send_size(&mut self, output: &mut OptionOutput) {
+        if !self.enabled || self.sent_size == Some(self.size) {
+            return;
+        }
+        let mut data = Vec::with_capacity(4);
+        data.extend_from_slice(&self.size.columns.to_be_bytes());
+        data.extend_from_slice(&self.size.rows.to_be_bytes());
+        output.subnegotiate(TelnetOption::NAWS.as_byte(), data);
+        self.sent_size = Some(self.size);
+    }
+}
+
+impl OptionHandler for NawsOption {
+    fn option(&self) -> u8 {
+        TelnetOption::NAWS.as_byte()
+    }
+
+    fn accept_local(&self) -> bool {
+        true
+    }
+
+    fn on_local_changed(&mut self, enabled: bool, output: &mut OptionOutput) {
+        self.enabled = enabled;
+        self.sent_size = None;
+        self.send_size(output);
+    }
+
+    fn on_window_resized(&mut self, size: WindowSize, output: &mut OptionOutput) {
+        self.size = size;
+        self.send_size(output);
+    }
+}
@@ -0,0 +1,154 @@
+use telnet::TelnetOption;
+
+use crate::net::encoding::TextEncoding;
+use crate::net::options::{OptionHandler, OptionOutput};
+
+/// TTYPE子协商中服务器请求终端类型
+const TTYPE_SEND: u8 = 1;
+/// TTYPE子协商中客户端回应终端类型
+const TTYPE_IS: u8 = 0;
+
+/// 向服务器报告的客户端名称
+const CLIENT_NAME: &str = "MUST";
+
+/// MTTS标志位，见 https://tintin.mudhalla.net/protocols/mtts/
+const MTTS_ANSI: u32 = 1;
+const MTTS_VT100: u32 = 2;
+const MTTS_UTF8: u32 = 4;
+const MTTS_256_COLORS: u32 = 8;
+const MTTS_NAWS: u32 = 32;
+const MTTS_TRUECOLOR: u32 = 256;
+const MTTS_SSL: u32 = 2048;
+
+/// TTYPE（RFC 1091）与MTTS。
+/// 服务器每次请求终端类型时，依次回应客户端名称、终端类型以及 "MTTS <标志位>"，
+/// 最后一项重复回应一次后重新开始，服务器据此判断列表已经结束
+pub struct TtypeOption {
+    /// 客户端实际支持的特性，与[`crate::screen`]和telnet层的能力保持一致
+    mtts_flags: u32,
+    /// 下一次回应的类型在列表中的位置
+    cycle_idx: usize,
+}
+
+impl TtypeOption {
+    pub fn new(encoding: TextEncoding, tls: bool) -> Self {
+        let mut mtts_flags = MTTS_ANSI | MTTS_VT100 | MTTS_256_COLORS | MTTS_NAWS | MTTS_TRUECOLOR;
+        if encoding == TextEncoding::Utf8 {
+            mtts_flags |= MTTS_UTF8;
+        }
+        if tls {
+            mtts_flags |= MTTS_SSL;
+        }
+        Self {
+            mtts_flags,
+            cycle_idx: 0,
+        }
+    }
+
+    fn terminal_types(&self) -> [String; 3] {
+        [
+            CLIENT_NAME.into(),
+            // MTTS约定的终端类型名称，表示支持真彩色
+            "ANSI-TRUECOLOR".into(),
+            format!("MTTS {}", self.mtts_flags),
+        ]
+    }
+}
+
+impl OptionHandler for TtypeOption {
+    fn option(&self) -> u8 {
+        TelnetOption::TTYPE.as_byte()
+    }
+
+    fn accept_local(&self) -> bool {
+        true
+    }
+
+    fn on_local_changed(&mut self, _enabled: bool, _output: &mut OptionOutput) {
+        self.cycle_idx = 0;
+    }
+
+    fn on_subnegotiation(&mut self, data: &[u8], output: &mut OptionOutput) {
+        if data.first() != Some(&TTYPE_SEND) {
+            return;
+        }
+        let types = self.terminal_types();
+        let last_idx = types.len() - 1;
+        let terminal_type = &types[self.cycle_idx.min(last_idx)];
+        let mut reply = vec![TTYPE_IS];
+        reply.extend_from_slice(terminal_type.as_bytes());
+        output.subnegotiate(self.option(), reply);
+        self.cycle_idx = if self.cycle_idx > last_idx {
+            // 最后一项已经重复回应过，从头开始新的一轮
+            0
+        } else {
+            self.cycle_idx + 1
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::net::options::TelnetCommand;
+
+    /// 服务器请求一次终端类型，返回客户端的回应
+    fn request(ttype: &mut TtypeOption) -> String {
+        let mut output = OptionOutput::default();
+        ttype.on_subnegotiation(&[TTYPE_SEND], &mut output);
+        let commands = output.take_commands();
+        let [TelnetCommand::Subnegotiate(option, data)] = commands.as_slice() else {
+            panic!("expected one subnegotiation");
+        };
+        assert_eq!(option.as_byte(), TelnetOption::TTYPE.as_byte());
+        assert_eq!(data[0], TTYPE_IS);
+        String::from_utf8(data[1..].to_vec()).unwrap()
+    }
+
+    #[test]
+    fn reply_cycle_repeats_last_type_then_restarts() {
+        let mut ttype = TtypeOption::new(TextEncoding::Gbk, false);
+        let flags = MTTS_ANSI | MTTS_VT100 | MTTS_256_COLORS | MTTS_NAWS | MTTS_TRUECOLOR;
+        let mtts = format!("MTTS {}", flags);
+        let replies: Vec<String> = (0..6).map(|_| request(&mut ttype)).collect();
+        assert_eq!(
+            replies,
+            [
+                "MUST",
+                "ANSI-TRUECOLOR",
+                &mtts,
+                &mtts,
+                "MUST",
+                "ANSI-TRUECOLOR"
+            ]
+        );
+        // 重新协商后从头开始
+        ttype.on_local_changed(true, &mut OptionOutput::default());
+        assert_eq!(request(&mut ttype), "MUST");
+    }
+
+    #[test]
+    fn mtts_flags_follow_encoding_and_tls() {
+        let base = MTTS_ANSI | MTTS_VT100 | MTTS_256_COLORS | MTTS_NAWS | MTTS_TRUECOLOR;
+        assert_eq!(TtypeOption::new(TextEncoding::Gbk, false).mtts_flags, base);
+        assert_eq!(
+            TtypeOption::new(TextEncoding::Utf8, false).mtts_flags,
+            base | MTTS_UTF8
+        );
+        assert_eq!(
+            TtypeOption::new(TextEncoding::Utf8, true).mtts_flags,
+            base | MTTS_UTF8 | MTTS_SSL
+        );
+        assert_eq!(base, 299);
+    }
+
+    #[test]
+    fn ignores_other_subnegotiations() {
+        let mut ttype = TtypeOption::new(TextEncoding::Utf8, false);
+        let mut output = OptionOutput::default();
+        ttype.on_subnegotiation(&[TTYPE_IS, b'x'], &mut output);
+        ttype.on_subnegotiation(&[], &mut output);
+        assert!(output.take_commands().is_empty());
+        assert_eq!(request(&mut ttype), "MUST");
+    }
+}
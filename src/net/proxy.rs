@@ -0,0 +1,402 @@
+use std::io::{self, Read, Write};
+use std::net::{IpAddr, TcpStream};
+
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+
+use crate::config::{ProxyConfig, ProxyKind};
+use crate::net::{connect_tcp, CONNECT_TIMEOUT};
+
+const SOCKS_VERSION: u8 = 5;
+const SOCKS_AUTH_NONE: u8 = 0;
+const SOCKS_AUTH_PASSWORD: u8 = 2;
+const SOCKS_AUTH_UNACCEPTABLE: u8 = 0xFF;
+/// 用户名密码认证（RFC 1929）的子协商版本
+const SOCKS_PASSWORD_VERSION: u8 = 1;
+const SOCKS_CMD_CONNECT: u8 = 1;
+const SOCKS_ATYP_IPV4: u8 = 1;
+const SOCKS_ATYP_DOMAIN: u8 = 3;
+const SOCKS_ATYP_IPV6: u8 = 4;
+
+/// HTTP CONNECT响应头的最大长度，防止代理服务器返回异常数据时无限读取
+const MAX_HTTP_RESPONSE_SIZE: usize = 8 * 1024;
+
+/// 连接代理服务器，并通过它建立到目标主机的隧道。
+/// 返回的连接在握手完成后与直接连接目标主机无异，TLS与telnet层在其上照常工作
+pub fn connect(proxy: &ProxyConfig, host: &str, port: u16) -> io::Result<TcpStream> {
+    let mut stream = connect_tcp(&proxy.host, proxy.port)?;
+    stream.set_read_timeout(Some(CONNECT_TIMEOUT))?;
+    stream.set_write_timeout(Some(CONNECT_TIMEOUT))?;
+    match proxy.kind {
+        ProxyKind::Socks5 => socks5_handshake(&mut stream, proxy, host, port)?,
+        ProxyKind::Http => http_connect(&mut stream, proxy, host, port)?,
+    }
+    stream.set_read_timeout(None)?;
+    stream.set_write_timeout(None)?;
+    Ok(stream)
+}
+
+/// SOCKS5（RFC 1928），配置了用户名时同时提供用户名密码认证（RFC 1929）
+fn socks5_handshake<S: Read + Write>(
+    stream: &mut S,
+    proxy: &ProxyConfig,
+    host: &str,
+    port: u16,
+) -> io::Result<()> {
+    let credentials = proxy
+        .username
+        .as_deref()
+        .map(|username| (username, proxy.password.as_deref().unwrap_or_default()));
+    let greeting: &[u8] = if credentials.is_some() {
+        &[SOCKS_VERSION, 2, SOCKS_AUTH_NONE, SOCKS_AUTH_PASSWORD]
+    } else {
+        &[SOCKS_VERSION, 1, SOCKS_AUTH_NONE]
+    };
+    stream.write_all(greeting)?;
+    let mut reply = [0u8; 2];
+    stream.read_exact(&mut reply)?;
+    if reply[0] != SOCKS_VERSION {
+        return Err(proxy_error("代理服务器不是SOCKS5服务器".into()));
+    }
+    match (reply[1], credentials) {
+        (SOCKS_AUTH_NONE, _) => {}
+        (SOCKS_AUTH_PASSWORD, Some((username, password))) => {
+            let mut request = vec![SOCKS_PASSWORD_VERSION];
+            request.extend(length_prefixed(username, "用户名")?);
+            request.extend(length_prefixed(password, "密码")?);
+            stream.write_all(&request)?;
+            stream.read_exact(&mut reply)?;
+            if reply[1] != 0 {
+                return Err(proxy_error("SOCKS5认证失败，请检查用户名和密码".into()));
+            }
+        }
+        (SOCKS_AUTH_UNACCEPTABLE, _) | (SOCKS_AUTH_PASSWORD, None) => {
+            return Err(proxy_error(
+                "SOCKS5代理服务器要求认证，请配置用户名和密码".into(),
+            ))
+        }
+        (method, _) => {
+            return Err(proxy_error(format!(
+                "SOCKS5代理服务器选择了不支持的认证方式：{}",
+                method
+            )))
+        }
+    }
+
+    // 主机名交由代理服务器解析，便于在只能访问代理的网络中使用
+    let mut request = vec![SOCKS_VERSION, SOCKS_CMD_CONNECT, 0];
+    match host.parse::<IpAddr>() {
+        Ok(IpAddr::V4(ip)) => {
+            request.push(SOCKS_ATYP_IPV4);
+            request.extend(ip.octets());
+        }
+        Ok(IpAddr::V6(ip)) => {
+            request.push(SOCKS_ATYP_IPV6);
+            request.extend(ip.octets());
+        }
+        Err(_) => {
+            request.push(SOCKS_ATYP_DOMAIN);
+            request.extend(length_prefixed(host, "主机名")?);
+        }
+    }
+    request.extend(port.to_be_bytes());
+    stream.write_all(&request)?;
+
+    let mut reply = [0u8; 4];
+    stream.read_exact(&mut reply)?;
+    if reply[1] != 0 {
+        return Err(proxy_error(format!(
+            "SOCKS5代理服务器无法连接目标主机：{}",
+            socks5_reply_message(reply[1])
+        )));
+    }
+    // 读取并丢弃代理服务器绑定的地址与端口
+    let address_len = match reply[3] {
+        SOCKS_ATYP_IPV4 => 4,
+        SOCKS_ATYP_IPV6 => 16,
+        SOCKS_ATYP_DOMAIN => {
+            let mut len = [0u8; 1];
+            stream.read_exact(&mut len)?;
+            len[0] as usize
+        }
+        atyp => {
+            return Err(proxy_error(format!(
+                "SOCKS5代理服务器返回了未知的地址类型：{}",
+                atyp
+            )))
+        }
+    };
+    let mut bound = vec![0u8; address_len + 2];
+    stream.read_exact(&mut bound)?;
+    Ok(())
+}
+
+/// SOCKS5中以一个字节表示长度的字符串
+fn length_prefixed(value: &str, name: &str) -> io::Result<Vec<u8>> {
+    let len = u8::try_from(value.len())
+        .map_err(|_| proxy_error(format!("SOCKS5的{}不能超过255个字节", name)))?;
+    let mut bytes = vec![len];
+    bytes.extend(value.as_bytes());
+    Ok(bytes)
+}
+
+fn socks5_reply_message(code: u8) -> &'static str {
+    match code {
+        1 => "代理服务器内部错误",
+        2 => "代理规则不允许该连接",
+        3 => "网络不可达",
+        4 => "主机不可达",
+        5 => "连接被拒绝",
+        6 => "TTL已过期",
+        7 => "不支持的命令",
+        8 => "不支持的地址类型",
+        _ => "未知错误",
+    }
+}
+
+/// 通过HTTP CONNECT方法建立隧道，配置了用户名时使用Basic认证
+fn http_connect<S: Read + Write>(
+    stream: &mut S,
+    proxy: &ProxyConfig,
+    host: &str,
+    port: u16,
+) -> io::Result<()> {
+    let authority = if host.contains(':') {
+        // IPv6地址需要使用方括号
+        format!("[{}]:{}", host, port)
+    } else {
+        format!("{}:{}", host, port)
+    };
+    let mut request = format!("CONNECT {0} HTTP/1.1\r\nHost: {0}\r\n", authority);
+    if let Some(username) = &proxy.username {
+        let credentials = format!(
+            "{}:{}",
+            username,
+            proxy.password.as_deref().unwrap_or_default()
+        );
+        request.push_str(&format!(
+            "Proxy-Authorization: Basic {}\r\n",
+            BASE64.encode(credentials)
+        ));
+    }
+    request.push_str("\r\n");
+    stream.write_all(request.as_bytes())?;
+
+    // 逐字节读取响应头，避免读走隧道建立后服务器发来的数据
+    let mut response = Vec::new();
+    let mut byte = [0u8; 1];
+    while !response.ends_with(b"\r\n\r\n") {
+        if response.len() >= MAX_HTTP_RESPONSE_SIZE {
+            return Err(proxy_error("HTTP代理服务器的响应过长".into()));
+        }
+        if stream.read(&mut byte)? == 0 {
+            return Err(proxy_error("HTTP代理服务器关闭了连接".into()));
+        }
+        response.push(byte[0]);
+    }
+    let response = String::from_utf8_lossy(&response);
+    let status_line = response.lines().next().unwrap_or_default();
+    let status = status_line.split_whitespace().nth(1).unwrap_or_default();
+    if !status.starts_with('2') {
+        return Err(proxy_error(format!(
+            "HTTP代理服务器拒绝了连接：{}",
+            status_line
+        )));
+    }
+    Ok(())
+}
+
+fn proxy_error(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::ConnectionRefused, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    /// 内存中的代理服务器连接，按顺序返回预先准备的响应，并记录客户端发送的数据
+    struct MockProxy {
+        replies: Cursor<Vec<u8>>,
+        written: Vec<u8>,
+    }
+
+    impl MockProxy {
+        fn new(replies: &[&[u8]]) -> Self {
+            Self {
+                replies: Cursor::new(replies.concat()),
+                written: Vec::new(),
+            }
+        }
+
+        /// 尚未被读取的响应数据
+        fn unread(&self) -> &[u8] {
+            &self.replies.get_ref()[self.replies.position() as usize..]
+        }
+    }
+
+    impl Read for MockProxy {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            self.replies.read(buf)
+        }
+    }
+
+    impl Write for MockProxy {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.written.extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn proxy(kind: ProxyKind, credentials: Option<(&str, &str)>) -> ProxyConfig {
+        ProxyConfig {
+            kind,
+            host: "127.0.0.1".into(),
+            port: 1080,
+            username: credentials.map(|(username, _)| username.into()),
+            password: credentials.map(|(_, password)| password.into()),
+        }
+    }
+
+    /// 连接成功的SOCKS5响应，绑定地址为IPv4
+    const SOCKS_CONNECTED: &[u8] = &[5, 0, 0, SOCKS_ATYP_IPV4, 10, 0, 0, 1, 0x1F, 0x90];
+
+    fn socks5(mock: &mut MockProxy, credentials: Option<(&str, &str)>) -> io::Result<()> {
+        let proxy = proxy(ProxyKind::Socks5, credentials);
+        socks5_handshake(mock, &proxy, "mud.example", 4000)
+    }
+
+    fn error_message(result: io::Result<()>) -> String {
+        result.unwrap_err().to_string()
+    }
+
+    #[test]
+    fn socks5_without_authentication() {
+        let mut mock = MockProxy::new(&[&[5, SOCKS_AUTH_NONE], SOCKS_CONNECTED, b"tunnel"]);
+        socks5(&mut mock, None).unwrap();
+        let mut expected = vec![5, 1, SOCKS_AUTH_NONE];
+        expected.extend([5, SOCKS_CMD_CONNECT, 0, SOCKS_ATYP_DOMAIN, 11]);
+        expected.extend(b"mud.example");
+        expected.extend(4000u16.to_be_bytes());
+        assert_eq!(mock.written, expected);
+        assert_eq!(mock.unread(), b"tunnel");
+    }
+
+    #[test]
+    fn socks5_password_authentication() {
+        let mut mock = MockProxy::new(&[&[5, SOCKS_AUTH_PASSWORD], &[1, 0], SOCKS_CONNECTED]);
+        socks5(&mut mock, Some(("user", "secret"))).unwrap();
+        let mut expected = vec![5, 2, SOCKS_AUTH_NONE, SOCKS_AUTH_PASSWORD];
+        expected.extend([SOCKS_PASSWORD_VERSION, 4]);
+        expected.extend(b"user");
+        expected.push(6);
+        expected.extend(b"secret");
+        assert_eq!(mock.written[..expected.len()], expected);
+
+        let mut mock = MockProxy::new(&[&[5, SOCKS_AUTH_PASSWORD], &[1, 1]]);
+        assert_eq!(
+            error_message(socks5(&mut mock, Some(("user", "wrong")))),
+            "SOCKS5认证失败，请检查用户名和密码"
+        );
+    }
+
+    #[test]
+    fn socks5_authentication_required() {
+        let required = "SOCKS5代理服务器要求认证，请配置用户名和密码";
+        let mut mock = MockProxy::new(&[&[5, SOCKS_AUTH_UNACCEPTABLE]]);
+        assert_eq!(error_message(socks5(&mut mock, None)), required);
+        let mut mock = MockProxy::new(&[&[5, SOCKS_AUTH_PASSWORD]]);
+        assert_eq!(error_message(socks5(&mut mock, None)), required);
+        let mut mock = MockProxy::new(&[&[4, 0]]);
+        assert_eq!(
+            error_message(socks5(&mut mock, None)),
+            "代理服务器不是SOCKS5服务器"
+        );
+    }
+
+    #[test]
+    fn socks5_reply_codes() {
+        for code in 1..=9 {
+            let mut mock = MockProxy::new(&[&[5, SOCKS_AUTH_NONE], &[5, code, 0, SOCKS_ATYP_IPV4]]);
+            let err = socks5(&mut mock, None).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::ConnectionRefused);
+            assert_eq!(
+                err.to_string(),
+                format!(
+                    "SOCKS5代理服务器无法连接目标主机：{}",
+                    socks5_reply_message(code)
+                )
+            );
+        }
+        assert_eq!(socks5_reply_message(5), "连接被拒绝");
+        assert_eq!(socks5_reply_message(9), "未知错误");
+    }
+
+    #[test]
+    fn socks5_addresses() {
+        // 目标为IPv6地址，代理服务器绑定的地址为域名
+        let mut mock = MockProxy::new(&[
+            &[5, SOCKS_AUTH_NONE],
+            &[5, 0, 0, SOCKS_ATYP_DOMAIN, 5],
+            b"proxy",
+            &[0, 80],
+            b"tunnel",
+        ]);
+        let proxy = proxy(ProxyKind::Socks5, None);
+        socks5_handshake(&mut mock, &proxy, "::1", 23).unwrap();
+        let mut request = vec![5, SOCKS_CMD_CONNECT, 0, SOCKS_ATYP_IPV6];
+        request.extend([0; 15]);
+        request.extend([1, 0, 23]);
+        assert_eq!(mock.written[3..], request);
+        assert_eq!(mock.unread(), b"tunnel");
+
+        let mut mock = MockProxy::new(&[&[5, SOCKS_AUTH_NONE], &[5, 0, 0, 9]]);
+        assert_eq!(
+            error_message(socks5(&mut mock, None)),
+            "SOCKS5代理服务器返回了未知的地址类型：9"
+        );
+    }
+
+    #[test]
+    fn http_connect_leaves_tunnel_data_unread() {
+        let response: &[u8] = b"HTTP/1.1 200 Connection established\r\nVia: proxy\r\n\r\n";
+        let mut mock = MockProxy::new(&[response, b"Welcome\r\n"]);
+        let proxy = proxy(ProxyKind::Http, Some(("user", "secret")));
+        http_connect(&mut mock, &proxy, "mud.example", 4000).unwrap();
+        assert_eq!(mock.unread(), b"Welcome\r\n");
+        assert_eq!(
+            String::from_utf8(mock.written).unwrap(),
+            "CONNECT mud.example:4000 HTTP/1.1\r\nHost: mud.example:4000\r\n\
+             Proxy-Authorization: Basic dXNlcjpzZWNyZXQ=\r\n\r\n"
+        );
+    }
+
+    #[test]
+    fn http_connect_errors() {
+        let proxy = proxy(ProxyKind::Http, None);
+        let mut mock = MockProxy::new(&[b"HTTP/1.1 407 Proxy Authentication Required\r\n\r\n"]);
+        assert_eq!(
+            error_message(http_connect(&mut mock, &proxy, "::1", 23)),
+            "HTTP代理服务器拒绝了连接：HTTP/1.1 407 Proxy Authentication Required"
+        );
+        assert!(String::from_utf8(mock.written)
+            .unwrap()
+            .starts_with("CONNECT [::1]:23 "));
+
+        let mut mock = MockProxy::new(&[b"HTTP/1.1 200 OK\r\n"]);
+        assert_eq!(
+            error_message(http_connect(&mut mock, &proxy, "mud.example", 23)),
+            "HTTP代理服务器关闭了连接"
+        );
+        let mut mock = MockProxy::new(&[&[b'a'; MAX_HTTP_RESPONSE_SIZE + 1]]);
+        assert_eq!(
+            error_message(http_connect(&mut mock, &proxy, "mud.example", 23)),
+            "HTTP代理服务器的响应过长"
+        );
+    }
+}
@@ -0,0 +1,53 @@
+use std::time::{Duration, Instant};
+
+/// 首次重连前的等待时间
+const INITIAL_DELAY: Duration = Duration::from_secs(2);
+/// 重连等待时间的上限
+const MAX_DELAY: Duration = Duration::from_secs(60);
+
+/// 断线重连的退避计时，每次重连失败后等待时间翻倍，直至上限
+pub struct Reconnect {
+    attempts: u32,
+    next_attempt_at: Option<Instant>,
+}
+
+impl Reconnect {
+    pub fn new() -> Self {
+        Self {
+            attempts: 0,
+            next_attempt_at: None,
+        }
+    }
+
+    /// 安排下一次重连，返回需要等待的时间
+    pub fn schedule(&mut self) -> Duration {
+        let delay = INITIAL_DELAY
+            .saturating_mul(2u32.saturating_pow(self.attempts))
+            .min(MAX_DELAY);
+        self.attempts += 1;
+        self.next_attempt_at = Some(Instant::now() + delay);
+        delay
+    }
+
+    /// 是否到了重连的时间，到期后会清除本次安排
+    pub fn take_due(&mut self) -> bool {
+        match self.next_attempt_at {
+            Some(at) if Instant::now() >= at => {
+                self.next_attempt_at = None;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// 取消尚未执行的重连，用于用户主动断开
+    pub fn cancel(&mut self) {
+        self.next_attempt_at = None;
+    }
+
+    /// 连接成功后重置退避
+    pub fn reset(&mut self) {
+        self.attempts = 0;
+        self.next_attempt_at = None;
+    }
+}
@@ -0,0 +1,522 @@
+use std::io::{self, Read, Write};
+use std::time::Duration;
+
+use flate2::{Compress, Compression, Decompress, FlushCompress, FlushDecompress, Status};
+
+use crate::net::options::mccp::{MCCP2, MCCP3};
+
+/// 包装底层的网络流。
+/// telnet库在读到EOF（服务器关闭连接）时不会报错，而是不断返回内部队列错误，
+/// 这里将EOF转换为明确的io错误，以便上层感知连接已断开
+pub struct EofAwareStream<S> {
+    inner: S,
+}
+
+impl<S> EofAwareStream<S> {
+    pub fn new(inner: S) -> Self {
+        Self { inner }
+    }
+}
+
+impl<S: Read> Read for EofAwareStream<S> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let size = self.inner.read(buf)?;
+        if size == 0 && !buf.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "服务器关闭了连接",
+            ));
+        }
+        Ok(size)
+    }
+}
+
+impl<S: Write> Write for EofAwareStream<S> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.inner.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+impl<S: telnet::Stream> telnet::Stream for EofAwareStream<S> {
+    fn set_nonblocking(&self, nonblocking: bool) -> io::Result<()> {
+        self.inner.set_nonblocking(nonblocking)
+    }
+
+    fn set_read_timeout(&self, dur: Option<Duration>) -> io::Result<()> {
+        self.inner.set_read_timeout(dur)
+    }
+}
+
+const IAC: u8 = 255;
+const SB: u8 = 250;
+const SE: u8 = 240;
+const WILL: u8 = 251;
+const DONT: u8 = 254;
+
+/// telnet字节流的解析状态，只用于判断命令的边界
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum FrameState {
+    Data,
+    Iac,
+    /// WILL/WONT/DO/DONT之后，等待选项字节
+    Negotiation,
+    /// SB之后，等待选项字节
+    SubnegotiationOption,
+    SubnegotiationData(u8),
+    SubnegotiationIac(u8),
+}
+
+/// 逐字节扫描的结果
+enum Scan {
+    /// 处于某个命令的中间
+    Partial,
+    /// 处于命令边界
+    Boundary,
+    /// 刚刚结束了一个子协商，同样处于命令边界
+    SubnegotiationEnd(u8),
+}
+
+/// 只关心命令边界的telnet解析器
+struct TelnetScanner {
+    state: FrameState,
+}
+
+impl TelnetScanner {
+    fn new() -> Self {
+        Self {
+            state: FrameState::Data,
+        }
+    }
+
+    fn advance(&mut self, byte: u8) -> Scan {
+        self.state = match (self.state, byte) {
+            (FrameState::Data, IAC) => FrameState::Iac,
+            (FrameState::Data, _) => FrameState::Data,
+            (FrameState::Iac, SB) => FrameState::SubnegotiationOption,
+            (FrameState::Iac, WILL..=DONT) => FrameState::Negotiation,
+            // IAC IAC（转义的255）以及其他单字节命令
+            (FrameState::Iac, _) => FrameState::Data,
+            (FrameState::Negotiation, _) => FrameState::Data,
+            (FrameState::SubnegotiationOption, option) => FrameState::SubnegotiationData(option),
+            (FrameState::SubnegotiationData(option), IAC) => FrameState::SubnegotiationIac(option),
+            (FrameState::SubnegotiationData(option), _) => FrameState::SubnegotiationData(option),
+            (FrameState::SubnegotiationIac(option), SE) => {
+                self.state = FrameState::Data;
+                return Scan::SubnegotiationEnd(option);
+            }
+            (FrameState::SubnegotiationIac(option), _) => FrameState::SubnegotiationData(option),
+        };
+        if self.state == FrameState::Data {
+            Scan::Boundary
+        } else {
+            Scan::Partial
+        }
+    }
+}
+
+/// 位于telnet库之下的数据流，负责两件事：
+///
+/// 1. 保证每次读取都在完整的telnet命令边界处结束。
+///    telnet库逐块解析数据，且不会保留跨块的解析状态，
+///    若一个子协商（如较大的GMCP数据包）被拆分在两次读取中，它将被丢弃，剩余部分还会被当作文本输出。
+///    这里将不完整的命令保留到下一次读取，与后续数据拼接完整后再交给telnet库。
+/// 2. MCCP压缩。服务器发送 IAC SB MCCP2 IAC SE 之后的数据均为zlib压缩数据，
+///    由于压缩开始的位置可能位于一次读取的中间，必须在telnet库解析之前完成解压；
+///    客户端发送 IAC SB MCCP3 IAC SE 之后，发送的数据同样需要压缩
+pub struct FramedStream<S> {
+    inner: S,
+    inbound: TelnetScanner,
+    outbound: TelnetScanner,
+    /// 已从底层读取（并解压）、尚未交给调用方的数据
+    pending: Vec<u8>,
+    /// pending中最后一个完整命令边界的位置
+    boundary: usize,
+    inflater: Option<Decompress>,
+    deflater: Option<Compress>,
+    /// 解压失败的错误，在交出此前已解出的数据之后再返回
+    inflate_error: Option<io::Error>,
+}
+
+impl<S> FramedStream<S> {
+    pub fn new(inner: S) -> Self {
+        Self {
+            inner,
+            inbound: TelnetScanner::new(),
+            outbound: TelnetScanner::new(),
+            pending: Vec::new(),
+            boundary: 0,
+            inflater: None,
+            deflater: None,
+            inflate_error: None,
+        }
+    }
+
+    /// 处理从底层读取的原始数据，压缩部分解压后再扫描
+    fn feed(&mut self, mut raw: &[u8]) -> io::Result<()> {
+        while !raw.is_empty() {
+            match self.inflater.as_mut() {
+                Some(inflater) => {
+                    let mut plain = Vec::with_capacity(raw.len() * 4);
+                    let result = inflate(inflater, raw, &mut plain);
+                    // 即使后面的数据已损坏，此前解压出的数据仍然有效
+                    self.scan_plain(&plain);
+                    let (consumed, finished) = result?;
+                    raw = &raw[consumed..];
+                    if finished {
+                        // 服务器结束了压缩，之后的数据为明文
+                        self.inflater = None;
+                    }
+                }
+                None => {
+                    let consumed = self.scan_plain(raw);
+                    raw = &raw[consumed..];
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// 扫描明文数据，返回处理的字节数。遇到MCCP2压缩开始的标记时停止，其后的数据需要解压
+    fn scan_plain(&mut self, bytes: &[u8]) -> usize {
+        for (idx, &byte) in bytes.iter().enumerate() {
+            let scan = self.inbound.advance(byte);
+            self.pending.push(byte);
+            match scan {
+                Scan::Partial => {}
+                Scan::Boundary => self.boundary = self.pending.len(),
+                Scan::SubnegotiationEnd(option) => {
+                    self.boundary = self.pending.len();
+                    if option == MCCP2 && self.inflater.is_none() {
+                        self.inflater = Some(Decompress::new(true));
+                        return idx + 1;
+                    }
+                }
+            }
+        }
+        bytes.len()
+    }
+
+    /// 扫描待发送的数据，返回需要明文发送的字节数，
+    /// 若其中包含MCCP3压缩开始的标记，则其后的数据需要压缩
+    fn scan_outbound(&mut self, bytes: &[u8]) -> usize {
+        for (idx, &byte) in bytes.iter().enumerate() {
+            if let Scan::SubnegotiationEnd(MCCP3) = self.outbound.advance(byte) {
+                self.deflater = Some(Compress::new(Compression::default(), true));
+                return idx + 1;
+            }
+        }
+        bytes.len()
+    }
+
+    /// 交出pending中不超过limit的完整命令
+    fn take_pending(&mut self, limit: usize, buf: &mut [u8]) -> usize {
+        let len = if self.boundary <= limit {
+            self.boundary
+        } else {
+            // 解压后的数据可能超出调用方的缓冲区，重新找到limit之内的最后一个边界
+            let mut scanner = TelnetScanner::new();
+            let last_boundary = self.pending[..limit]
+                .iter()
+                .enumerate()
+                .filter(|(_, &byte)| !matches!(scanner.advance(byte), Scan::Partial))
+                .last()
+                .map_or(0, |(idx, _)| idx + 1);
+            if last_boundary == 0 {
+                limit
+            } else {
+                last_boundary
+            }
+        };
+        self.split_pending(len, buf)
+    }
+
+    fn split_pending(&mut self, len: usize, buf: &mut [u8]) -> usize {
+        buf[..len].copy_from_slice(&self.pending[..len]);
+        self.pending.drain(..len);
+        self.boundary = self.boundary.saturating_sub(len);
+        len
+    }
+}
+
+impl<S: Read> Read for FramedStream<S> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        loop {
+            if self.boundary > 0 {
+                return Ok(self.take_pending(buf.len(), buf));
+            }
+            if let Some(err) = self.inflate_error.take() {
+                return Err(err);
+            }
+            if self.pending.len() >= buf.len() {
+                // 单个命令比调用方的缓冲区还大，无法保持完整，只能拆分交出
+                return Ok(self.split_pending(buf.len(), buf));
+            }
+            // 读取的数据量不超过调用方缓冲区的剩余空间，保证未压缩时完整的命令总能一次交出
+            let mut chunk = vec![0; buf.len() - self.pending.len()];
+            let size = self.inner.read(&mut chunk)?;
+            if size == 0 {
+                return Ok(0);
+            }
+            if let Err(err) = self.feed(&chunk[..size]) {
+                self.inflater = None;
+                self.inflate_error = Some(err);
+            }
+        }
+    }
+}
+
+impl<S: Write> Write for FramedStream<S> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let plain_len = if self.deflater.is_some() {
+            0
+        } else {
+            self.scan_outbound(buf)
+        };
+        self.inner.write_all(&buf[..plain_len])?;
+        if let Some(deflater) = self.deflater.as_mut() {
+            let compressed = deflate(deflater, &buf[plain_len..])?;
+            self.inner.write_all(&compressed)?;
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+impl<S: telnet::Stream> telnet::Stream for FramedStream<S> {
+    fn set_nonblocking(&self, nonblocking: bool) -> io::Result<()> {
+        self.inner.set_nonblocking(nonblocking)
+    }
+
+    fn set_read_timeout(&self, dur: Option<Duration>) -> io::Result<()> {
+        self.inner.set_read_timeout(dur)
+    }
+}
+
+/// 解压一段数据并追加到output，返回消耗的输入字节数，以及压缩流是否已经结束。
+/// 压缩数据损坏时返回错误，此时连接已无法继续使用，但损坏之前解压出的数据仍保留在output中
+fn inflate(
+    inflater: &mut Decompress,
+    input: &[u8],
+    output: &mut Vec<u8>,
+) -> io::Result<(usize, bool)> {
+    let start = inflater.total_in();
+    loop {
+        if output.len() == output.capacity() {
+            output.reserve(output.capacity().max(4096));
+        }
+        let consumed = (inflater.total_in() - start) as usize;
+        let produced = output.len();
+        let status = inflater
+            .decompress_vec(&input[consumed..], output, FlushDecompress::Sync)
+            .map_err(|err| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("MCCP压缩数据损坏：{}", err),
+                )
+            })?;
+        let total_consumed = (inflater.total_in() - start) as usize;
+        if let Status::StreamEnd = status {
+            return Ok((total_consumed, true));
+        }
+        let no_progress = total_consumed == consumed && output.len() == produced;
+        let drained = total_consumed == input.len() && output.len() < output.capacity();
+        if drained || no_progress {
+            return Ok((total_consumed, false));
+        }
+    }
+}
+
+/// 压缩一段待发送的数据，并同步刷新，保证服务器能立即解压出完整的命令
+fn deflate(deflater: &mut Compress, input: &[u8]) -> io::Result<Vec<u8>> {
+    let start = deflater.total_in();
+    let mut output = Vec::with_capacity(input.len() + 64);
+    loop {
+        if output.len() == output.capacity() {
+            output.reserve(output.capacity().max(64));
+        }
+        let consumed = (deflater.total_in() - start) as usize;
+        deflater
+            .compress_vec(&input[consumed..], &mut output, FlushCompress::Sync)
+            .map_err(io::Error::other)?;
+        let total_consumed = (deflater.total_in() - start) as usize;
+        if total_consumed == input.len() && output.len() < output.capacity() {
+            return Ok(output);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::VecDeque;
+
+    use flate2::write::ZlibEncoder;
+
+    use super::*;
+
+    /// 内存中的数据流，每次读取返回一个预先准备的数据块，数据块用完后视为EOF
+    struct MockStream {
+        reads: VecDeque<Vec<u8>>,
+        written: Vec<u8>,
+    }
+
+    impl MockStream {
+        fn new(reads: Vec<Vec<u8>>) -> Self {
+            Self {
+                reads: reads.into(),
+                written: Vec::new(),
+            }
+        }
+    }
+
+    impl Read for MockStream {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let Some(mut chunk) = self.reads.pop_front() else {
+                return Ok(0);
+            };
+            if chunk.len() > buf.len() {
+                self.reads.push_front(chunk.split_off(buf.len()));
+            }
+            buf[..chunk.len()].copy_from_slice(&chunk);
+            Ok(chunk.len())
+        }
+    }
+
+    impl Write for MockStream {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.written.extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    const MCCP2_START: [u8; 5] = [IAC, SB, MCCP2, IAC, SE];
+
+    /// 依次读取直到EOF或出错，返回每次读取的数据以及最后的错误
+    fn read_all(stream: &mut FramedStream<MockStream>) -> (Vec<Vec<u8>>, Option<io::Error>) {
+        let mut reads = Vec::new();
+        let mut buf = [0; 1024];
+        loop {
+            match stream.read(&mut buf) {
+                Ok(0) => return (reads, None),
+                Ok(size) => reads.push(buf[..size].to_vec()),
+                Err(err) => return (reads, Some(err)),
+            }
+        }
+    }
+
+    /// 同步刷新但没有结束的压缩流，与服务器持续压缩时发送的数据一致
+    fn compress_sync(data: &[u8]) -> Vec<u8> {
+        deflate(&mut Compress::new(Compression::default(), true), data).unwrap()
+    }
+
+    /// 已经结束的压缩流
+    fn compress_finished(data: &[u8]) -> Vec<u8> {
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(data).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    fn concat(parts: &[&[u8]]) -> Vec<u8> {
+        parts.concat()
+    }
+
+    #[test]
+    fn compression_starts_mid_chunk() {
+        let compressed_part = concat(&[b"world\r\n", &[IAC, SB, 201], b"Core.Ping", &[IAC, SE]]);
+        let chunk = concat(&[b"hello\r\n", &MCCP2_START, &compress_sync(&compressed_part)]);
+        let mut stream = FramedStream::new(MockStream::new(vec![chunk]));
+        let (reads, err) = read_all(&mut stream);
+        assert!(err.is_none());
+        assert_eq!(
+            reads.concat(),
+            concat(&[b"hello\r\n", &MCCP2_START, &compressed_part])
+        );
+    }
+
+    #[test]
+    fn stream_end_switches_back_to_plaintext() {
+        let chunk = concat(&[
+            &MCCP2_START,
+            &compress_finished(b"compressed\r\n"),
+            b"plain\r\n",
+        ]);
+        let mut stream = FramedStream::new(MockStream::new(vec![chunk, b"more\r\n".to_vec()]));
+        let (reads, err) = read_all(&mut stream);
+        assert!(err.is_none());
+        assert_eq!(
+            reads.concat(),
+            concat(&[&MCCP2_START, b"compressed\r\n", b"plain\r\n", b"more\r\n"])
+        );
+        assert!(stream.inflater.is_none());
+    }
+
+    #[test]
+    fn corrupted_stream_fails_after_decoded_data() {
+        let chunk = concat(&[
+            b"before\r\n",
+            &MCCP2_START,
+            &compress_sync(b"decoded\r\n"),
+            &[0xFF; 8],
+        ]);
+        let mut stream = FramedStream::new(MockStream::new(vec![chunk]));
+        let (reads, err) = read_all(&mut stream);
+        assert_eq!(
+            reads.concat(),
+            concat(&[b"before\r\n", &MCCP2_START, b"decoded\r\n"])
+        );
+        assert_eq!(err.unwrap().kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn partial_commands_are_held_across_reads() {
+        let chunks = vec![
+            concat(&[b"abc", &[IAC, SB, 201], b"Core"]),
+            concat(&[b".Ping", &[IAC]]),
+            concat(&[&[SE], b"def", &[IAC]]),
+            concat(&[&[IAC], b"g", &[IAC, WILL]]),
+            vec![201],
+        ];
+        let mut stream = FramedStream::new(MockStream::new(chunks));
+        let (reads, err) = read_all(&mut stream);
+        assert!(err.is_none());
+        assert_eq!(
+            reads,
+            [
+                b"abc".to_vec(),
+                concat(&[&[IAC, SB, 201], b"Core.Ping", &[IAC, SE], b"def"]),
+                concat(&[&[IAC, IAC], b"g"]),
+                vec![IAC, WILL, 201],
+            ]
+        );
+    }
+
+    #[test]
+    fn mccp3_starts_after_split_marker() {
+        let mut stream = FramedStream::new(MockStream::new(Vec::new()));
+        // telnet库分三次写入子协商：IAC SB 选项、数据、IAC SE
+        stream.write_all(&[IAC, SB, MCCP3]).unwrap();
+        stream.write_all(&[]).unwrap();
+        assert!(stream.deflater.is_none());
+        stream.write_all(&[IAC, SE]).unwrap();
+        assert!(stream.deflater.is_some());
+        stream.write_all(b"look\r\n").unwrap();
+        let written = &stream.inner.written;
+        assert_eq!(written[..5], [IAC, SB, MCCP3, IAC, SE]);
+        let mut plain = Vec::new();
+        let (consumed, finished) =
+            inflate(&mut Decompress::new(true), &written[5..], &mut plain).unwrap();
+        assert_eq!(consumed, written.len() - 5);
+        assert_eq!(plain, b"look\r\n");
+        assert!(!finished);
+    }
+}
@@ -0,0 +1,142 @@
+use std::io;
+use std::sync::mpsc::{self, Receiver, Sender, TryRecvError};
+use std::thread;
+use std::time::Duration;
+
+use crate::config::ConnectionProfile;
+use crate::net::options::naws::WindowSize;
+use crate::net::{Connection, ConnectionEvent};
+
+/// 网络线程没有数据可读时等待的最长时间，也是界面提交的命令最多需要等待的时间
+const IDLE_WAIT: Duration = Duration::from_millis(10);
+
+/// 界面发给网络线程的命令
+enum ConnectionCommand {
+    SendLine(String),
+    SetWindowSize(WindowSize),
+    SendMsdp(String, Vec<String>),
+}
+
+/// 界面持有的连接句柄。
+/// 连接的建立、读写、解压与解码都在独立的网络线程中进行，
+/// 界面只通过通道提交命令并接收处理好的[`ConnectionEvent`]，不受帧率影响。
+/// 丢弃句柄即断开连接，网络线程随之结束
+pub struct ConnectionHandle {
+    commands: Sender<ConnectionCommand>,
+    events: Receiver<ConnectionEvent>,
+}
+
+impl ConnectionHandle {
+    /// 启动网络线程连接服务器，连接结果通过事件返回
+    pub fn open(profile: ConnectionProfile, window_size: WindowSize) -> io::Result<Self> {
+        let (command_tx, command_rx) = mpsc::channel();
+        let (event_tx, event_rx) = mpsc::channel();
+        thread::Builder::new()
+            .name(format!("net-{}", profile.name))
+            .spawn(move || run(&profile, window_size, command_rx, event_tx))?;
+        Ok(Self {
+            commands: command_tx,
+            events: event_rx,
+        })
+    }
+
+    /// 按连接编码发送一行命令，自动追加回车换行
+    pub fn send_line(&self, line: &str) {
+        self.send(ConnectionCommand::SendLine(line.into()));
+    }
+
+    /// 更新终端窗口大小，在NAWS启用时告知服务器
+    pub fn set_window_size(&self, size: WindowSize) {
+        self.send(ConnectionCommand::SetWindowSize(size));
+    }
+
+    /// 发送MSDP命令，如 REPORT HEALTH
+    pub fn send_msdp(&self, command: &str, values: &[String]) {
+        self.send(ConnectionCommand::SendMsdp(command.into(), values.to_vec()));
+    }
+
+    /// 网络线程已经结束时，命令被丢弃，线程结束前发出的事件会告知原因
+    fn send(&self, command: ConnectionCommand) {
+        let _ = self.commands.send(command);
+    }
+
+    /// 取出网络线程产生的全部事件
+    pub fn poll(&self) -> Vec<ConnectionEvent> {
+        let mut events = Vec::new();
+        loop {
+            match self.events.try_recv() {
+                Ok(event) => events.push(event),
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    // 网络线程总是以Failed或Closed结束，否则说明线程意外退出
+                    if !matches!(
+                        events.last(),
+                        Some(ConnectionEvent::Failed(_) | ConnectionEvent::Closed(_))
+                    ) {
+                        events.push(ConnectionEvent::Closed("网络线程意外退出".into()));
+                    }
+                    break;
+                }
+            }
+        }
+        events
+    }
+}
+
+/// 网络线程：建立连接，然后交替执行界面提交的命令与读取服务器数据，直到连接断开或界面丢弃句柄
+fn run(
+    profile: &ConnectionProfile,
+    window_size: WindowSize,
+    commands: Receiver<ConnectionCommand>,
+    events: Sender<ConnectionEvent>,
+) {
+    let mut connection = match Connection::open(profile) {
+        Ok(connection) => connection,
+        Err(err) => {
+            let _ = events.send(ConnectionEvent::Failed(err.to_string()));
+            return;
+        }
+    };
+    // 服务器要求NAWS时需要知道当前的窗口大小，这里不会发送任何数据
+    let _ = connection.set_window_size(window_size);
+    if events.send(ConnectionEvent::Connected).is_err() {
+        return;
+    }
+    loop {
+        loop {
+            let command = match commands.try_recv() {
+                Ok(command) => command,
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => return,
+            };
+            if let Err(err) = execute(&mut connection, command, &events) {
+                let reason = format!("发送数据失败：{}", err);
+                let _ = events.send(ConnectionEvent::Closed(reason));
+                return;
+            }
+        }
+        for event in connection.poll(IDLE_WAIT) {
+            let closed = matches!(event, ConnectionEvent::Closed(_));
+            if events.send(event).is_err() || closed {
+                return;
+            }
+        }
+    }
+}
+
+fn execute(
+    connection: &mut Connection,
+    command: ConnectionCommand,
+    events: &Sender<ConnectionEvent>,
+) -> io::Result<()> {
+    match command {
+        ConnectionCommand::SendLine(line) => connection.send_line(&line).map(|_| ()),
+        ConnectionCommand::SetWindowSize(size) => connection.set_window_size(size),
+        ConnectionCommand::SendMsdp(command, values) => {
+            if !connection.send_msdp(&command, &values)? {
+                let _ = events.send(ConnectionEvent::Error("服务器未启用MSDP".into()));
+            }
+            Ok(())
+        }
+    }
+}
@@ -0,0 +1,147 @@
+use serde_json::{Map, Value};
+
+/// 路径的分隔符，如 Char.Vitals.hp
+const PATH_SEPARATOR: char = '.';
+
+/// 服务器通过GMCP、MSDP等带外协议发送的数据，以树形结构保存各项的最新值，
+/// 供界面与自动化功能按路径查询。MSDP的变量保存在 MSDP 路径下，如 MSDP.HEALTH
+pub struct OobStore {
+    root: Value,
+}
+
+impl OobStore {
+    pub fn new() -> Self {
+        Self {
+            root: Value::Object(Map::new()),
+        }
+    }
+
+    /// 用新数据整体替换指定路径的数据，用于每次都发送完整数据的数据包，
+    /// 新数据中没有的字段随之删除
+    pub fn update(&mut self, path: &str, value: Value) {
+        *self.node_mut(path) = value;
+    }
+
+    /// 将数据合并到指定路径，用于只发送变化字段的数据包。
+    /// 若原有数据与新数据均为对象，则逐个字段合并；否则直接替换
+    pub fn merge(&mut self, path: &str, value: Value) {
+        match (self.node_mut(path), value) {
+            (Value::Object(old), Value::Object(new)) => old.extend(new),
+            (node, value) => *node = value,
+        }
+    }
+
+    /// 指定路径的节点，路径上缺少的节点以及不是对象的节点被替换为空对象
+    fn node_mut(&mut self, path: &str) -> &mut Value {
+        let mut node = &mut self.root;
+        for key in path.split(PATH_SEPARATOR).filter(|k| !k.is_empty()) {
+            if !node.is_object() {
+                *node = Value::Object(Map::new());
+            }
+            node = node
+                .as_object_mut()
+                .expect("node is object")
+                .entry(key)
+                .or_insert(Value::Null);
+        }
+        node
+    }
+
+    /// 按路径查询数据，路径为空时返回整棵树。数组可以使用下标，如 Room.Info.exits.0
+    pub fn get(&self, path: &str) -> Option<&Value> {
+        let mut node = &self.root;
+        for key in path.split(PATH_SEPARATOR).filter(|k| !k.is_empty()) {
+            node = match node {
+                Value::Object(map) => map.get(key)?,
+                Value::Array(items) => items.get(key.parse::<usize>().ok()?)?,
+                _ => return None,
+            };
+        }
+        Some(node)
+    }
+
+    /// 断开连接后清空数据，避免展示过期的值
+    pub fn clear(&mut self) {
+        self.root = Value::Object(Map::new());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn objects_are_merged_field_by_field() {
+        let mut store = OobStore::new();
+        store.merge("Char.Vitals", json!({ "hp": 100, "mp": 50 }));
+        store.merge("Char.Vitals", json!({ "hp": 80 }));
+        assert_eq!(
+            store.get("Char.Vitals"),
+            Some(&json!({ "hp": 80, "mp": 50 }))
+        );
+        assert_eq!(store.get("Char.Vitals.hp"), Some(&json!(80)));
+        assert_eq!(
+            store.get("Char"),
+            Some(&json!({ "Vitals": { "hp": 80, "mp": 50 } }))
+        );
+    }
+
+    #[test]
+    fn snapshot_update_drops_missing_fields() {
+        let mut store = OobStore::new();
+        store.update(
+            "Room.Info",
+            json!({ "name": "客店", "exits": { "n": 1 }, "dark": true }),
+        );
+        store.update("Room.Info", json!({ "name": "广场", "exits": { "s": 2 } }));
+        assert_eq!(
+            store.get("Room.Info"),
+            Some(&json!({ "name": "广场", "exits": { "s": 2 } }))
+        );
+        assert_eq!(store.get("Room.Info.dark"), None);
+        // 合并时非对象的数据同样被替换
+        store.merge("Room.Info.exits", json!(["east"]));
+        assert_eq!(store.get("Room.Info.exits"), Some(&json!(["east"])));
+    }
+
+    #[test]
+    fn non_object_values_are_replaced() {
+        let mut store = OobStore::new();
+        store.update("Room.Info.exits", json!(["north", "south"]));
+        store.update("Room.Info.exits", json!(["east"]));
+        assert_eq!(store.get("Room.Info.exits"), Some(&json!(["east"])));
+        // 对象与非对象之间互相替换
+        store.update("Room.Info", json!("黑暗中"));
+        assert_eq!(store.get("Room.Info"), Some(&json!("黑暗中")));
+        assert_eq!(store.get("Room.Info.exits"), None);
+        store.update("Room.Info.name", json!("广场"));
+        assert_eq!(store.get("Room.Info"), Some(&json!({ "name": "广场" })));
+    }
+
+    #[test]
+    fn path_lookup() {
+        let mut store = OobStore::new();
+        store.update("MSDP.ROOM", json!({ "EXITS": ["n", "s"], "NAME": "客店" }));
+        assert_eq!(store.get("MSDP.ROOM.EXITS.1"), Some(&json!("s")));
+        assert_eq!(store.get("MSDP.ROOM.EXITS.2"), None);
+        assert_eq!(store.get("MSDP.ROOM.EXITS.x"), None);
+        assert_eq!(store.get("MSDP.ROOM.NAME.0"), None);
+        assert_eq!(store.get("MSDP.MISSING"), None);
+        // 空路径以及多余的分隔符
+        assert_eq!(
+            store.get("").unwrap()["MSDP"]["ROOM"]["NAME"],
+            json!("客店")
+        );
+        assert_eq!(store.get(".MSDP..ROOM.NAME."), Some(&json!("客店")));
+    }
+
+    #[test]
+    fn clear_removes_everything() {
+        let mut store = OobStore::new();
+        store.update("Char.Vitals.hp", json!(1));
+        store.clear();
+        assert_eq!(store.get("Char"), None);
+        assert_eq!(store.get(""), Some(&json!({})));
+    }
+}
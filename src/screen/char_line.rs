@@ -1,23 +1,121 @@
+use crate::config::Theme;
+use crate::utils::is_wide_char;
+
+/// 终端中的一行字符。
+/// 列以单元格为单位，非宽体字符占一个单元格，宽体字符占两个
 pub struct CharLine {
     char_codes: Vec<CharCode>,
+    /// 该行占用的单元格数
+    width: usize,
+    /// 该行是否为服务器的提示符（以 IAC GA/EOR 而非换行结束）
+    prompt: bool,
 }
 
 impl CharLine {
     pub fn new() -> Self {
         Self {
-            char_codes: Vec::new()
+            char_codes: Vec::new(),
+            width: 0,
+            prompt: false,
         }
     }
 
+    pub fn set_prompt(&mut self, prompt: bool) {
+        self.prompt = prompt;
+    }
+
+    pub fn is_prompt(&self) -> bool {
+        self.prompt
+    }
+
     pub fn push_code(&mut self, char_code: CharCode) {
+        self.width += cell_width(char_code.c);
         self.char_codes.push(char_code)
     }
 
     pub fn char_codes(&self) -> &Vec<CharCode> {
         &self.char_codes
     }
+
+    /// 在第col个单元格处写入字符，覆盖该处原有的内容，行宽不足时以空格补齐。
+    /// 返回写入后的下一个单元格
+    pub fn write(&mut self, col: usize, char_code: CharCode) -> usize {
+        let next_col = col + cell_width(char_code.c);
+        if col == self.width {
+            // 绝大多数情况下是在行尾追加
+            self.push_code(char_code);
+            return next_col;
+        }
+        let start = self.split_at(col);
+        let end = if next_col < self.width {
+            self.split_at(next_col)
+        } else {
+            self.char_codes.len()
+        };
+        self.char_codes.splice(start..end, [char_code]);
+        self.width = self.width.max(next_col);
+        next_col
+    }
+
+    /// 清除第col个单元格（含）之后的内容
+    pub fn erase_from(&mut self, col: usize) {
+        if col < self.width {
+            let idx = self.split_at(col);
+            self.char_codes.truncate(idx);
+            self.width = col;
+        }
+    }
+
+    /// 将第col个单元格（含）之前的内容替换为空格
+    pub fn erase_to(&mut self, col: usize) {
+        let cells = (col + 1).min(self.width);
+        let end = if cells < self.width {
+            self.split_at(cells)
+        } else {
+            self.char_codes.len()
+        };
+        let blank = CharCode::new(' ', CharCodeStyle::new());
+        self.char_codes.splice(0..end, std::iter::repeat_n(blank, cells));
+    }
+
+    pub fn clear(&mut self) {
+        self.char_codes.clear();
+        self.width = 0;
+    }
+
+    /// 确保第col个单元格处是两个字符的边界，返回该处字符的下标。
+    /// 落在宽体字符中间时将其拆为两个空格，超出行宽时以空格补齐
+    fn split_at(&mut self, col: usize) -> usize {
+        let mut cell = 0;
+        for idx in 0..self.char_codes.len() {
+            if cell == col {
+                return idx;
+            }
+            let width = cell_width(self.char_codes[idx].c);
+            if cell + width > col {
+                let blank = CharCode::new(' ', self.char_codes[idx].style);
+                self.char_codes[idx] = blank;
+                self.char_codes.insert(idx + 1, blank);
+                return idx + 1;
+            }
+            cell += width;
+        }
+        while self.width < col {
+            self.push_code(CharCode::new(' ', CharCodeStyle::new()));
+        }
+        self.char_codes.len()
+    }
 }
 
+fn cell_width(c: char) -> usize {
+    if is_wide_char(c) {
+        2
+    } else {
+        1
+    }
+}
+
+#[derive(Copy, Clone)]
 pub struct CharCode {
     pub c: char,
     pub style: CharCodeStyle,
@@ -32,29 +130,72 @@ impl CharCode {
     }
 }
 
-#[derive(Copy, Clone)]
-pub struct TerminalCharColor([u8; 4]);
+/// 终端字符颜色，与xterm的颜色模型一致：
+/// Indexed为256色调色板中的颜色（0-15为ANSI标准色与亮色，16-231为6×6×6色彩立方，232-255为灰阶），
+/// Rgb为24位真彩色
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum TerminalCharColor {
+    Indexed(u8),
+    Rgb(u8, u8, u8),
+}
+
+/// 色彩立方每个分量的6个取值
+const CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
 
 impl TerminalCharColor {
-    pub const WHITE: TerminalCharColor = TerminalCharColor([255, 255, 255, 255]);
-    pub const BLACK: TerminalCharColor = TerminalCharColor([0, 0, 0, 255]);
-    pub const RED: TerminalCharColor = TerminalCharColor([255, 0, 0, 255]);
-    pub const GREEN: TerminalCharColor = TerminalCharColor([0, 255, 0, 255]);
-    pub const BLUE: TerminalCharColor = TerminalCharColor([0, 0, 255, 255]);
-    pub const YELLOW: TerminalCharColor = TerminalCharColor([255, 255, 0, 255]);
-    pub const PURPLE: TerminalCharColor = TerminalCharColor([128, 0, 128, 255]);
-    pub const CYAN: TerminalCharColor = TerminalCharColor([0, 255, 255, 255]);
-
-    pub fn get_rgba(&self) -> [u8; 4] {
-        self.0
+    pub const RED: TerminalCharColor = TerminalCharColor::Indexed(1);
+    pub const CYAN: TerminalCharColor = TerminalCharColor::Indexed(6);
+
+    /// 颜色的RGBA值，其中前16种颜色由主题决定
+    pub fn get_rgba(&self, theme: &Theme) -> [u8; 4] {
+        let [r, g, b] = match *self {
+            TerminalCharColor::Indexed(idx @ 0..=15) => theme.ansi[idx as usize].0,
+            TerminalCharColor::Indexed(idx) => indexed_rgb(idx),
+            TerminalCharColor::Rgb(r, g, b) => [r, g, b],
+        };
+        [r, g, b, 255]
+    }
+
+    /// 设置该颜色的SGR参数，base为30时设置前景色，为40时设置背景色
+    fn sgr_params(&self, base: u8) -> String {
+        match *self {
+            TerminalCharColor::Indexed(idx @ 0..=7) => (base + idx).to_string(),
+            TerminalCharColor::Indexed(idx @ 8..=15) => (base + 60 + idx - 8).to_string(),
+            TerminalCharColor::Indexed(idx) => format!("{};5;{}", base + 8, idx),
+            TerminalCharColor::Rgb(r, g, b) => format!("{};2;{};{};{}", base + 8, r, g, b),
+        }
     }
 }
 
-#[derive(Copy, Clone)]
+/// 256色调色板中第idx个（16及以后）颜色的RGB值
+fn indexed_rgb(idx: u8) -> [u8; 3] {
+    if idx >= 232 {
+        let level = 8 + (idx - 232) * 10;
+        return [level, level, level];
+    }
+    let idx = idx as usize - 16;
+    [
+        CUBE_LEVELS[idx / 36],
+        CUBE_LEVELS[idx / 6 % 6],
+        CUBE_LEVELS[idx % 6],
+    ]
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
 pub struct CharCodeStyle {
     pub bold: bool,
-    pub underline: bool,
-    pub fg_color: TerminalCharColor,
+    /// 暗淡，与粗体共用SGR 22关闭
+    pub faint: bool,
+    pub italic: bool,
+    pub underline: Underline,
+    pub blink: bool,
+    /// 反显，绘制时交换前景色与背景色
+    pub reverse: bool,
+    /// 隐藏，字符照常占位但不绘制
+    pub conceal: bool,
+    pub strikethrough: bool,
+    /// 前景色与背景色，None表示使用主题的默认颜色
+    pub fg_color: Option<TerminalCharColor>,
     pub bg_color: Option<TerminalCharColor>,
 }
 
@@ -62,9 +203,48 @@ impl CharCodeStyle {
     pub fn new() -> Self {
         Self {
             bold: false,
-            underline: false,
-            fg_color: TerminalCharColor::WHITE,
+            faint: false,
+            italic: false,
+            underline: Underline::None,
+            blink: false,
+            reverse: false,
+            conceal: false,
+            strikethrough: false,
+            fg_color: None,
             bg_color: None,
         }
     }
-}
\ No newline at end of file
+
+    /// 切换到该样式的SGR序列，先重置所有属性再逐一设置
+    pub fn sgr(&self) -> String {
+        let mut params = vec!["0".to_string()];
+        let flags = [
+            (self.bold, "1"),
+            (self.faint, "2"),
+            (self.italic, "3"),
+            (self.underline == Underline::Single, "4"),
+            (self.underline == Underline::Double, "21"),
+            (self.blink, "5"),
+            (self.reverse, "7"),
+            (self.conceal, "8"),
+            (self.strikethrough, "9"),
+        ];
+        params.extend(
+            flags
+                .into_iter()
+                .filter(|(on, _)| *on)
+                .map(|(_, param)| param.to_string()),
+        );
+        params.extend(self.fg_color.map(|color| color.sgr_params(30)));
+        params.extend(self.bg_color.map(|color| color.sgr_params(40)));
+        format!("\x1b[{}m", params.join(";"))
+    }
+}
+
+/// 下划线的样式
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Underline {
+    None,
+    Single,
+    Double,
+}
@@ -1,9 +1,33 @@
 use vte::{Params, Perform};
-use crate::screen::char_line::{CharCode, CharCodeStyle, CharLine, TerminalCharColor};
+use crate::screen::char_line::{CharCode, CharCodeStyle, CharLine, TerminalCharColor, Underline};
 
+/// 制表位的间隔
+const TAB_WIDTH: usize = 8;
+
+/// 将服务器输出的字符与ANSI转义序列解析为带样式的字符行。
+/// 所有的行构成回滚缓冲区，其中最后rows行即为终端屏幕，光标定位、擦除等操作都作用在屏幕上
 pub struct CharResolver {
     char_lines: Vec<CharLine>,
     current_code_style: CharCodeStyle,
+    /// 提示符不以换行结尾，收到提示符后服务器的后续输出需要另起一行
+    prompt_break_pending: bool,
+    /// 终端屏幕以单元格为单位的大小
+    columns: usize,
+    rows: usize,
+    /// 光标所在的行在char_lines中的下标
+    cursor_line: usize,
+    /// 光标所在的单元格
+    cursor_col: usize,
+    /// ESC 7 或 CSI s 保存的光标位置（屏幕中的行、列）以及样式
+    saved_cursor: Option<(usize, usize, CharCodeStyle)>,
+    /// CSI r 设置的滚动区域（屏幕中的首行、末行），为None时整个屏幕滚动，滚出的行进入回滚缓冲区
+    scroll_region: Option<(usize, usize)>,
+    /// 是否收到了尚未处理的响铃字符
+    bell: bool,
+    /// 回滚缓冲区最多保留的行数，不含屏幕，超出时丢弃最早的行，默认不限制
+    scrollback_limit: usize,
+    /// 累计丢弃的行数，用于在行被丢弃后换算行的位置
+    dropped_lines: usize,
 }
 
 impl CharResolver {
@@ -11,81 +35,970 @@ impl CharResolver {
         Self {
             char_lines: Vec::new(),
             current_code_style: CharCodeStyle::new(),
+            prompt_break_pending: false,
+            columns: 80,
+            rows: 24,
+            cursor_line: 0,
+            cursor_col: 0,
+            saved_cursor: None,
+            scroll_region: None,
+            bell: false,
+            scrollback_limit: usize::MAX,
+            dropped_lines: 0,
+        }
+    }
+
+    pub fn set_scrollback_limit(&mut self, limit: usize) {
+        self.scrollback_limit = limit;
+        self.trim_scrollback();
+    }
+
+    /// 累计从回滚缓冲区中丢弃的行数
+    pub fn dropped_lines(&self) -> usize {
+        self.dropped_lines
+    }
+
+    /// 取出自上次调用以来是否收到过响铃字符
+    pub fn take_bell(&mut self) -> bool {
+        std::mem::take(&mut self.bell)
+    }
+
+    /// 更新终端屏幕的大小，滚动区域随之失效。
+    /// 屏幕变矮后光标可能位于屏幕首行之上，此时移到屏幕首行
+    pub fn set_size(&mut self, columns: usize, rows: usize) {
+        self.columns = columns.max(1);
+        self.rows = rows.max(1);
+        self.scroll_region = None;
+        self.cursor_line = self.cursor_line.max(self.screen_top());
+        self.cursor_col = self.cursor_col.min(self.columns - 1);
+    }
+
+    /// 将当前行标记为提示符。空行不是提示符，已标记过的行（如服务器同时发送GA与EOR）也无需重复处理
+    pub fn mark_prompt(&mut self) {
+        if let Some(line) = self.char_lines.get_mut(self.cursor_line) {
+            if !line.char_codes().is_empty() && !line.is_prompt() {
+                line.set_prompt(true);
+                self.prompt_break_pending = true;
+            }
+        }
+    }
+
+    /// 将当前行作为提示符取出，其位置留给后续的输出，用于将提示符固定显示
+    pub fn take_prompt_line(&mut self) -> Option<CharLine> {
+        let line = self.char_lines.get_mut(self.cursor_line)?;
+        if line.char_codes().is_empty() {
+            return None;
         }
+        let mut line = std::mem::replace(line, CharLine::new());
+        line.set_prompt(true);
+        self.cursor_col = 0;
+        Some(line)
+    }
+
+    /// 本地回显用户输入之前调用，使回显紧跟在提示符之后，而不是另起一行
+    pub fn continue_prompt_line(&mut self) {
+        self.prompt_break_pending = false;
     }
 
     pub fn get_lines(&self) -> &[CharLine] {
         &self.char_lines
     }
+
+    /// 输出一行客户端自身的提示信息（如连接状态），与服务器的输出分行展示
+    pub fn push_client_line(&mut self, text: &str, style: CharCodeStyle) {
+        let need_new_line = self
+            .char_lines
+            .last()
+            .is_none_or(|line| !line.char_codes().is_empty());
+        if need_new_line {
+            self.char_lines.push(CharLine::new());
+        }
+        self.prompt_break_pending = false;
+        if let Some(line) = self.char_lines.last_mut() {
+            for c in text.chars() {
+                line.push_code(CharCode::new(c, style));
+            }
+        }
+        // 服务器后续的输出从新的一行开始
+        self.char_lines.push(CharLine::new());
+        self.cursor_line = self.char_lines.len() - 1;
+        self.cursor_col = 0;
+        self.trim_scrollback();
+    }
+
+    /// 回滚缓冲区超出上限时丢弃最早的行
+    fn trim_scrollback(&mut self) {
+        let max_lines = self.rows.saturating_add(self.scrollback_limit);
+        if self.char_lines.len() > max_lines {
+            self.drop_lines(self.char_lines.len() - max_lines);
+        }
+    }
+
+    /// 丢弃最早的count行，光标所在的行保持不变
+    fn drop_lines(&mut self, count: usize) {
+        self.char_lines.drain(..count);
+        self.cursor_line = self.cursor_line.saturating_sub(count);
+        self.dropped_lines += count;
+    }
+
+    /// 屏幕首行在char_lines中的下标
+    fn screen_top(&self) -> usize {
+        self.char_lines.len().saturating_sub(self.rows)
+    }
+
+    /// 光标在屏幕中所在的行
+    fn cursor_row(&self) -> usize {
+        self.cursor_line.saturating_sub(self.screen_top())
+    }
+
+    /// 将光标移动到屏幕中的某一行，屏幕尚未被填满时补齐空行
+    fn set_cursor_row(&mut self, row: usize) {
+        self.cursor_line = self.screen_top() + row.min(self.rows - 1);
+        self.ensure_line(self.cursor_line);
+    }
+
+    fn set_cursor_col(&mut self, col: usize) {
+        self.cursor_col = col.min(self.columns - 1);
+    }
+
+    fn ensure_line(&mut self, line_idx: usize) {
+        while self.char_lines.len() <= line_idx {
+            self.char_lines.push(CharLine::new());
+        }
+    }
+
+    fn current_line(&mut self) -> &mut CharLine {
+        self.ensure_line(self.cursor_line);
+        &mut self.char_lines[self.cursor_line]
+    }
+
+    /// 滚动区域，未设置时为整个屏幕
+    fn region(&self) -> (usize, usize) {
+        self.scroll_region.unwrap_or((0, self.rows - 1))
+    }
+
+    /// 换行。MUD服务器常常只发送\n，因此换行的同时光标回到行首。
+    /// 光标位于滚动区域底部时，区域内的内容上移一行；未设置滚动区域时追加新行，屏幕首行进入回滚缓冲区
+    fn line_feed(&mut self) {
+        self.prompt_break_pending = false;
+        self.cursor_col = 0;
+        if let Some((top, bottom)) = self.scroll_region {
+            if self.cursor_row() == bottom {
+                self.scroll_up(top, bottom, 1);
+                return;
+            }
+        }
+        if self.cursor_line + 1 < self.char_lines.len() {
+            self.cursor_line += 1;
+        } else {
+            self.char_lines.push(CharLine::new());
+            self.cursor_line = self.char_lines.len() - 1;
+            self.trim_scrollback();
+        }
+    }
+
+    /// 反向换行，光标位于滚动区域顶部时，区域内的内容下移一行
+    fn reverse_line_feed(&mut self) {
+        let (top, bottom) = self.region();
+        let row = self.cursor_row();
+        if row == top {
+            self.scroll_down(top, bottom, 1);
+        } else {
+            self.set_cursor_row(row.saturating_sub(1));
+        }
+    }
+
+    /// 屏幕中top到bottom行的内容上移count行，底部补充空行
+    fn scroll_up(&mut self, top: usize, bottom: usize, count: usize) {
+        let screen_top = self.screen_top();
+        self.ensure_line(screen_top + bottom);
+        for _ in 0..count.min(bottom + 1 - top) {
+            self.char_lines.remove(screen_top + top);
+            self.char_lines.insert(screen_top + bottom, CharLine::new());
+        }
+    }
+
+    /// 屏幕中top到bottom行的内容下移count行，顶部补充空行
+    fn scroll_down(&mut self, top: usize, bottom: usize, count: usize) {
+        let screen_top = self.screen_top();
+        self.ensure_line(screen_top + bottom);
+        for _ in 0..count.min(bottom + 1 - top) {
+            self.char_lines.remove(screen_top + bottom);
+            self.char_lines.insert(screen_top + top, CharLine::new());
+        }
+    }
+
+    /// 擦除屏幕（ED）
+    fn erase_in_display(&mut self, mode: u16) {
+        let cursor_col = self.cursor_col;
+        match mode {
+            0 => {
+                self.current_line().erase_from(cursor_col);
+                for line in self.char_lines.iter_mut().skip(self.cursor_line + 1) {
+                    line.clear();
+                }
+            }
+            1 => {
+                let screen_top = self.screen_top();
+                for line in &mut self.char_lines[screen_top..self.cursor_line] {
+                    line.clear();
+                }
+                self.current_line().erase_to(cursor_col);
+            }
+            2 => {
+                // 与多数终端一样，将屏幕的内容整体滚入回滚缓冲区而不是直接丢弃，光标位置不变
+                let row = self.cursor_row();
+                for _ in 0..self.rows {
+                    self.char_lines.push(CharLine::new());
+                }
+                self.trim_scrollback();
+                self.set_cursor_row(row);
+            }
+            3 => {
+                // 清除回滚缓冲区，只保留屏幕
+                self.drop_lines(self.screen_top());
+            }
+            _ => {}
+        }
+    }
+
+    /// 擦除光标所在的行（EL）
+    fn erase_in_line(&mut self, mode: u16) {
+        let cursor_col = self.cursor_col;
+        match mode {
+            0 => self.current_line().erase_from(cursor_col),
+            1 => self.current_line().erase_to(cursor_col),
+            2 => self.current_line().clear(),
+            _ => {}
+        }
+    }
+
+    fn save_cursor(&mut self) {
+        self.saved_cursor = Some((self.cursor_row(), self.cursor_col, self.current_code_style));
+    }
+
+    fn restore_cursor(&mut self) {
+        let (row, col, style) = self
+            .saved_cursor
+            .unwrap_or((0, 0, CharCodeStyle::new()));
+        self.set_cursor_row(row);
+        self.set_cursor_col(col);
+        self.current_code_style = style;
+    }
+
+    /// 设置滚动区域（DECSTBM），参数为从1开始的首行与末行，光标随之回到屏幕左上角
+    fn set_scroll_region(&mut self, top: usize, bottom: usize) {
+        let top = top.max(1) - 1;
+        let bottom = if bottom == 0 { self.rows } else { bottom.min(self.rows) } - 1;
+        if top < bottom {
+            self.scroll_region = if top == 0 && bottom == self.rows - 1 {
+                None
+            } else {
+                Some((top, bottom))
+            };
+            self.set_cursor_row(0);
+            self.cursor_col = 0;
+        }
+    }
 }
 
 impl Perform for CharResolver {
     fn print(&mut self, c: char) {
-        if let Some(last_line) = self.char_lines.last_mut() {
-            let c = if c == '\u{0000}' { ' ' } else { c };
-            last_line.push_code(CharCode::new(c, self.current_code_style.clone()));
+        if self.prompt_break_pending {
+            self.line_feed();
         }
+        let c = if c == '\u{0000}' { ' ' } else { c };
+        let char_code = CharCode::new(c, self.current_code_style);
+        let cursor_col = self.cursor_col;
+        self.cursor_col = self.current_line().write(cursor_col, char_code);
     }
 
     fn execute(&mut self, byte: u8) {
-        if byte == 0x0A {
-            // /r/n => 0x0D,0x0A，新一行
-            let new_line = CharLine::new();
-            self.char_lines.push(new_line);
+        match byte {
+            // BEL，由界面按配置提醒用户
+            0x07 => self.bell = true,
+            // BS，光标左移一格，不擦除字符
+            0x08 => self.cursor_col = self.cursor_col.saturating_sub(1),
+            // HT，光标移动到下一个制表位
+            0x09 => self.set_cursor_col((self.cursor_col / TAB_WIDTH + 1) * TAB_WIDTH),
+            // /r/n => 0x0D,0x0A，新一行。提示符之后的换行即是提示符的结束，不必再另起一行
+            0x0A => self.line_feed(),
+            // CR，光标回到行首，之后的输出覆盖该行原有的内容
+            0x0D => self.cursor_col = 0,
+            _ => {}
         }
     }
 
-    fn csi_dispatch(&mut self, params: &Params, _intermediates: &[u8], _ignore: bool, action: char) {
+    fn csi_dispatch(&mut self, params: &Params, intermediates: &[u8], _ignore: bool, action: char) {
+        // 带有中间字符的序列（如 CSI ? 25 h）是终端模式设置，与字符行无关
+        if !intermediates.is_empty() {
+            return;
+        }
+        // 光标移动类序列中，参数缺省或为0时视为1
+        let count = param_or(params, 0, 1) as usize;
+        let row = self.cursor_row();
         match action {
             // 在ANSI转义序列中，以 m 结尾的一般是指文本样式、颜色或其他可视属性的设置。
-            'm' => {
-                self.current_code_style = self.resolve_style(params);
+            'm' => self.apply_style(params),
+            // CUU/CUD/CUF/CUB，光标上下左右移动
+            'A' => self.set_cursor_row(row.saturating_sub(count)),
+            'B' => self.set_cursor_row(row + count),
+            'C' => self.set_cursor_col(self.cursor_col + count),
+            'D' => self.set_cursor_col(self.cursor_col.saturating_sub(count)),
+            // CNL/CPL，光标移动到下/上若干行的行首
+            'E' => {
+                self.set_cursor_row(row + count);
+                self.cursor_col = 0;
             }
+            'F' => {
+                self.set_cursor_row(row.saturating_sub(count));
+                self.cursor_col = 0;
+            }
+            // CHA，光标移动到当前行的某一列
+            'G' => self.set_cursor_col(count - 1),
+            // VPA，光标移动到某一行，列不变
+            'd' => self.set_cursor_row(count - 1),
+            // CUP/HVP，光标定位到某行某列
+            'H' | 'f' => {
+                self.set_cursor_row(count - 1);
+                self.set_cursor_col(param_or(params, 1, 1) as usize - 1);
+            }
+            'J' => self.erase_in_display(param_or(params, 0, 0)),
+            'K' => self.erase_in_line(param_or(params, 0, 0)),
+            // IL/DL，在光标所在行插入/删除若干行，只影响光标所在的滚动区域
+            'L' | 'M' => {
+                let (top, bottom) = self.region();
+                if (top..=bottom).contains(&row) {
+                    if action == 'L' {
+                        self.scroll_down(row, bottom, count);
+                    } else {
+                        self.scroll_up(row, bottom, count);
+                    }
+                    self.cursor_col = 0;
+                }
+            }
+            'r' => self.set_scroll_region(
+                param_or(params, 0, 1) as usize,
+                param_or(params, 1, 0) as usize,
+            ),
+            's' => self.save_cursor(),
+            'u' => self.restore_cursor(),
             _ => {}
         }
     }
+
+    fn esc_dispatch(&mut self, intermediates: &[u8], _ignore: bool, byte: u8) {
+        if !intermediates.is_empty() {
+            return;
+        }
+        match byte {
+            b'7' => self.save_cursor(),
+            b'8' => self.restore_cursor(),
+            // RI，反向换行
+            b'M' => self.reverse_line_feed(),
+            _ => {}
+        }
+    }
+}
+
+/// 第idx个参数的值，缺省或为0时返回default
+fn param_or(params: &Params, idx: usize, default: u16) -> u16 {
+    params
+        .iter()
+        .nth(idx)
+        .and_then(|param| param.first().copied())
+        .filter(|val| *val != 0)
+        .unwrap_or(default)
 }
 
 impl CharResolver {
-    const fn get_terminal_color(color_num: u16) -> TerminalCharColor {
-        match color_num {
-            // 30前景色黑色，40背景色黑色
-            30 | 40 => TerminalCharColor::BLACK,
-            31 | 41 => TerminalCharColor::RED,
-            32 | 42 => TerminalCharColor::GREEN,
-            33 | 43 => TerminalCharColor::YELLOW,
-            34 | 44 => TerminalCharColor::BLUE,
-            35 | 45 => TerminalCharColor::PURPLE,
-            36 | 46 => TerminalCharColor::CYAN,
-            37 | 47 | _ => TerminalCharColor::WHITE,
-        }
-    }
-
-    fn resolve_style(&self, params: &Params) -> CharCodeStyle {
-        let mut style = CharCodeStyle::new();
-        for param in params.iter() {
-            if param.len() == 0 {
-                continue;
-            }
-            for sub_param in param.iter() {
-                let val = *sub_param;
-                if val >= 30 && val <= 37 {
-                    style.fg_color = CharResolver::get_terminal_color(val)
-                } else if val >= 40 && val <= 47 {
-                    style.bg_color = Some(CharResolver::get_terminal_color(val))
-                } else if val == 4 {
-                    style.underline = true;
-                } else if val == 24 {
-                    style.underline = false;
-                } else if val == 1 {
-                    style.bold = true;
-                } else if val == 22 {
+    /// 在当前样式的基础上应用SGR参数，未涉及的属性保持不变。
+    /// 没有参数（ESC[m）或参数为空（如 ESC[;1m 中的第一个）时等同于0，即重置样式
+    fn apply_style(&mut self, params: &Params) {
+        let style = &mut self.current_code_style;
+        if params.is_empty() {
+            *style = CharCodeStyle::new();
+        }
+        let mut iter = params.iter();
+        while let Some(param) = iter.next() {
+            let val = param.first().copied().unwrap_or(0);
+            match val {
+                0 => *style = CharCodeStyle::new(),
+                1 => style.bold = true,
+                2 => style.faint = true,
+                3 => style.italic = true,
+                // 冒号形式 4:n 指定下划线样式，曲线、点线等其他样式按单下划线处理
+                4 => {
+                    style.underline = match param.get(1) {
+                        Some(0) => Underline::None,
+                        Some(2) => Underline::Double,
+                        _ => Underline::Single,
+                    }
+                }
+                // 6为快速闪烁，不作区分
+                5 | 6 => style.blink = true,
+                7 => style.reverse = true,
+                8 => style.conceal = true,
+                9 => style.strikethrough = true,
+                21 => style.underline = Underline::Double,
+                22 => {
                     style.bold = false;
-                } else if val == 0 {
-                    style = CharCodeStyle::new()
+                    style.faint = false;
+                }
+                23 => style.italic = false,
+                24 => style.underline = Underline::None,
+                25 => style.blink = false,
+                27 => style.reverse = false,
+                28 => style.conceal = false,
+                29 => style.strikethrough = false,
+                30..=37 => style.fg_color = Some(TerminalCharColor::Indexed((val - 30) as u8)),
+                38 => {
+                    if let Some(color) = CharResolver::extended_color(param, &mut iter) {
+                        style.fg_color = Some(color);
+                    }
+                }
+                39 => style.fg_color = None,
+                40..=47 => style.bg_color = Some(TerminalCharColor::Indexed((val - 40) as u8)),
+                48 => {
+                    if let Some(color) = CharResolver::extended_color(param, &mut iter) {
+                        style.bg_color = Some(color);
+                    }
+                }
+                49 => style.bg_color = None,
+                90..=97 => {
+                    style.fg_color = Some(TerminalCharColor::Indexed((val - 90 + 8) as u8))
                 }
+                100..=107 => {
+                    style.bg_color = Some(TerminalCharColor::Indexed((val - 100 + 8) as u8))
+                }
+                _ => {}
             }
         }
-        style
     }
-}
\ No newline at end of file
+
+    /// 解析38/48之后的扩展颜色：5;n 为256色，2;r;g;b 为真彩色。
+    /// 冒号分隔的形式（38:5:n、38:2::r:g:b）中参数都在param内，
+    /// 分号分隔的形式则从后续参数中依次取出。参数不完整时返回None
+    fn extended_color<'a>(
+        param: &[u16],
+        rest: &mut impl Iterator<Item = &'a [u16]>,
+    ) -> Option<TerminalCharColor> {
+        if param.len() > 1 {
+            return match param[1..] {
+                [5, idx, ..] => Some(TerminalCharColor::Indexed(idx.min(255) as u8)),
+                // ITU T.416中颜色分量前还有一个色彩空间标识，多数情况下为空
+                [2, _, r, g, b, ..] | [2, r, g, b] => Some(CharResolver::rgb(r, g, b)),
+                _ => None,
+            };
+        }
+        let mut next = || rest.next().map(|p| p.first().copied().unwrap_or(0));
+        match next()? {
+            5 => Some(TerminalCharColor::Indexed(next()?.min(255) as u8)),
+            2 => {
+                let (r, g, b) = (next()?, next()?, next()?);
+                Some(CharResolver::rgb(r, g, b))
+            }
+            _ => None,
+        }
+    }
+
+    fn rgb(r: u16, g: u16, b: u16) -> TerminalCharColor {
+        TerminalCharColor::Rgb(r.min(255) as u8, g.min(255) as u8, b.min(255) as u8)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Theme;
+
+    /// 以columns×rows大小的屏幕解析字节流
+    fn resolve(columns: usize, rows: usize, input: &[u8]) -> CharResolver {
+        let mut resolver = CharResolver::new();
+        resolver.set_size(columns, rows);
+        let mut parser = vte::Parser::new();
+        for byte in input {
+            parser.advance(&mut resolver, *byte);
+        }
+        resolver
+    }
+
+    fn lines(resolver: &CharResolver) -> Vec<String> {
+        resolver
+            .get_lines()
+            .iter()
+            .map(|line| line.char_codes().iter().map(|cc| cc.c).collect())
+            .collect()
+    }
+
+    /// 屏幕上的各行，即最后rows行
+    fn screen(resolver: &CharResolver) -> Vec<String> {
+        let lines = lines(resolver);
+        lines[resolver.screen_top()..].to_vec()
+    }
+
+    #[test]
+    fn plain_text_and_line_feed() {
+        let resolver = resolve(80, 24, b"hello\nworld");
+        assert_eq!(lines(&resolver), ["hello", "world"]);
+        assert_eq!((resolver.cursor_line, resolver.cursor_col), (1, 5));
+    }
+
+    #[test]
+    fn cursor_position_overwrites() {
+        let resolver = resolve(80, 24, b"abcdef\x1b[1;3HXY");
+        assert_eq!(lines(&resolver), ["abXYef"]);
+        assert_eq!(resolver.cursor_col, 4);
+    }
+
+    #[test]
+    fn cursor_position_defaults_to_home() {
+        let resolver = resolve(80, 24, b"one\ntwo\x1b[Hx\x1b[;2fy");
+        assert_eq!(lines(&resolver), ["xye", "two"]);
+    }
+
+    #[test]
+    fn cursor_position_pads_short_lines() {
+        let resolver = resolve(80, 24, b"\x1b[3;5H*");
+        assert_eq!(lines(&resolver), ["", "", "    *"]);
+    }
+
+    #[test]
+    fn cursor_position_is_relative_to_screen() {
+        let resolver = resolve(10, 3, b"1\n2\n3\n4\n5\x1b[1;1H#");
+        assert_eq!(lines(&resolver), ["1", "2", "#", "4", "5"]);
+    }
+
+    #[test]
+    fn cursor_position_is_clamped() {
+        let resolver = resolve(10, 3, b"\x1b[99;99Hx");
+        assert_eq!(lines(&resolver), ["", "", "         x"]);
+    }
+
+    #[test]
+    fn relative_cursor_movement() {
+        let resolver = resolve(80, 24, b"\x1b[3;3H\x1b[2A\x1b[3CA\x1b[2BB\x1b[4DC\x1b[AD");
+        assert_eq!(lines(&resolver), ["     A", "    D", "   C  B"]);
+    }
+
+    #[test]
+    fn cursor_movement_stops_at_screen_edges() {
+        let resolver = resolve(5, 3, b"\x1b[10Ax\x1b[10Dy\x1b[10Cz\x1b[10B\x1b[10Dw");
+        assert_eq!(screen(&resolver), ["y   z", "", "w"]);
+    }
+
+    #[test]
+    fn next_and_previous_line_and_column() {
+        let resolver = resolve(80, 24, b"abc\x1b[2Ed\x1b[Fe\x1b[5Gf\x1b[3dg");
+        assert_eq!(lines(&resolver), ["abc", "e   f", "d    g"]);
+    }
+
+    #[test]
+    fn erase_in_line() {
+        let resolver = resolve(80, 24, b"abcdef\x1b[4G\x1b[K\nabcdef\x1b[4G\x1b[1K\nabcdef\x1b[2K");
+        assert_eq!(lines(&resolver), ["abc", "    ef", ""]);
+    }
+
+    #[test]
+    fn erase_below_and_above() {
+        let resolver = resolve(80, 3, b"111\n222\n333\x1b[2;2H\x1b[J");
+        assert_eq!(lines(&resolver), ["111", "2", ""]);
+        let resolver = resolve(80, 3, b"111\n222\n333\x1b[2;2H\x1b[1J");
+        assert_eq!(lines(&resolver), ["", "  2", "333"]);
+    }
+
+    #[test]
+    fn shrinking_screen_keeps_cursor_on_screen() {
+        let input: Vec<String> = (1..=30).map(|n| n.to_string()).collect();
+        let mut resolver = resolve(80, 24, input.join("\n").as_bytes());
+        let mut parser = vte::Parser::new();
+        for byte in b"\x1b[2;1H" {
+            parser.advance(&mut resolver, *byte);
+        }
+        assert_eq!(resolver.cursor_line, 7);
+        resolver.set_size(80, 10);
+        assert_eq!(resolver.cursor_line, resolver.screen_top());
+        // 擦除光标之上的内容、光标上移以及擦除整行都不会越过屏幕首行
+        for byte in b"\x1b[1J\x1b[Ax\x1b[5B\x1b[2K\x1b[1K" {
+            parser.advance(&mut resolver, *byte);
+        }
+        assert_eq!(
+            screen(&resolver),
+            ["x1", "22", "23", "24", "25", "", "27", "28", "29", "30"]
+        );
+        assert_eq!(lines(&resolver)[7], "8");
+    }
+
+    #[test]
+    fn erase_display_scrolls_into_scrollback() {
+        let resolver = resolve(80, 2, b"old\x1b[2J\x1b[Hnew");
+        assert_eq!(lines(&resolver), ["old", "new", ""]);
+        assert_eq!(screen(&resolver), ["new", ""]);
+    }
+
+    #[test]
+    fn erase_scrollback() {
+        let resolver = resolve(80, 2, b"1\n2\n3\x1b[3Jx");
+        assert_eq!(lines(&resolver), ["2", "3x"]);
+    }
+
+    #[test]
+    fn scrollback_limit_drops_oldest_lines() {
+        let mut resolver = resolve(80, 2, b"");
+        resolver.set_scrollback_limit(3);
+        let mut parser = vte::Parser::new();
+        for byte in b"1\n2\n3\n4\n5\n6\n7\x1b[1;1Hx" {
+            parser.advance(&mut resolver, *byte);
+        }
+        assert_eq!(lines(&resolver), ["3", "4", "5", "x", "7"]);
+        assert_eq!(resolver.dropped_lines(), 2);
+        // 客户端信息同样计入回滚缓冲区
+        resolver.push_client_line("info", CharCodeStyle::new());
+        assert_eq!(lines(&resolver), ["5", "x", "7", "info", ""]);
+        assert_eq!(resolver.dropped_lines(), 4);
+    }
+
+    #[test]
+    fn erase_scrollback_counts_dropped_lines() {
+        let resolver = resolve(80, 2, b"1\n2\n3\n4\x1b[3J");
+        assert_eq!(lines(&resolver), ["3", "4"]);
+        assert_eq!(resolver.dropped_lines(), 2);
+    }
+
+    #[test]
+    fn save_and_restore_cursor() {
+        let resolver = resolve(80, 24, b"ab\x1b[s\x1b[5;5Hx\x1b[uc\x1b7\ny\x1b8d");
+        assert_eq!(lines(&resolver)[0], "abcd");
+        let resolver = resolve(80, 24, b"\x1b[31m\x1b7\x1b[0m\x1b8x");
+        let style = resolver.get_lines()[0].char_codes()[0].style;
+        assert_eq!(style.fg_color, Some(TerminalCharColor::RED));
+    }
+
+    #[test]
+    fn scroll_region_keeps_lines_outside() {
+        let resolver = resolve(80, 4, b"head\n1\n2\nfoot\x1b[2;3r\x1b[3;1H\n3\n4");
+        assert_eq!(lines(&resolver), ["head", "3", "4", "foot"]);
+    }
+
+    #[test]
+    fn reverse_index_scrolls_region_down() {
+        let resolver = resolve(80, 4, b"head\n1\n2\nfoot\x1b[2;3r\x1b[2;1H\x1bMnew");
+        assert_eq!(lines(&resolver), ["head", "new", "1", "foot"]);
+    }
+
+    #[test]
+    fn full_screen_region_keeps_scrollback() {
+        let resolver = resolve(80, 2, b"\x1b[1;2r1\n2\n3");
+        assert_eq!(lines(&resolver), ["1", "2", "3"]);
+    }
+
+    #[test]
+    fn insert_and_delete_lines() {
+        let resolver = resolve(80, 3, b"1\n2\n3\x1b[2H\x1b[L");
+        assert_eq!(lines(&resolver), ["1", "", "2"]);
+        let resolver = resolve(80, 3, b"1\n2\n3\x1b[1H\x1b[2M");
+        assert_eq!(lines(&resolver), ["3", "", ""]);
+    }
+
+    #[test]
+    fn overwrite_wide_chars() {
+        // 覆盖宽体字符的一半时，另一半以空格代替
+        let resolver = resolve(80, 24, "你好世界\x1b[1;2Hab\x1b[1;7Hc".as_bytes());
+        assert_eq!(lines(&resolver), [" ab 世c "]);
+        let resolver = resolve(80, 24, "abcd\x1b[1;2H中".as_bytes());
+        assert_eq!(lines(&resolver), ["a中d"]);
+    }
+
+    #[test]
+    fn carriage_return_overwrites() {
+        let resolver = resolve(80, 24, b"10%\r50%\r100%\r\n");
+        assert_eq!(lines(&resolver), ["100%", ""]);
+        let resolver = resolve(80, 24, b"abcdef\rXY");
+        assert_eq!(lines(&resolver), ["XYcdef"]);
+    }
+
+    #[test]
+    fn line_feed_after_carriage_return() {
+        let resolver = resolve(80, 24, b"one\r\ntwo\n\rthree");
+        assert_eq!(lines(&resolver), ["one", "two", "three"]);
+    }
+
+    #[test]
+    fn backspace_moves_left() {
+        let resolver = resolve(80, 24, b"abc\x08\x08X\x08\x08\x08\x08Y");
+        assert_eq!(lines(&resolver), ["YXc"]);
+    }
+
+    #[test]
+    fn tab_advances_to_next_stop() {
+        let resolver = resolve(80, 24, b"a\tb\tc\n12345678\td");
+        assert_eq!(lines(&resolver), ["a       b       c", "12345678        d"]);
+        // 制表位不会超出屏幕的最后一列
+        let resolver = resolve(10, 24, b"\t\t\tx");
+        assert_eq!(lines(&resolver), ["         x"]);
+    }
+
+    #[test]
+    fn bell_is_reported_once() {
+        let mut resolver = resolve(80, 24, b"ding\x07!");
+        assert_eq!(lines(&resolver), ["ding!"]);
+        assert!(resolver.take_bell());
+        assert!(!resolver.take_bell());
+    }
+
+    /// 解析字节流后第一行各字符的样式
+    fn styles(input: &[u8]) -> Vec<CharCodeStyle> {
+        let resolver = resolve(80, 24, input);
+        resolver.get_lines()[0]
+            .char_codes()
+            .iter()
+            .map(|cc| cc.style)
+            .collect()
+    }
+
+    fn style(
+        bold: bool,
+        underline: bool,
+        fg: Option<TerminalCharColor>,
+        bg: Option<TerminalCharColor>,
+    ) -> CharCodeStyle {
+        CharCodeStyle {
+            bold,
+            underline: if underline {
+                Underline::Single
+            } else {
+                Underline::None
+            },
+            fg_color: fg,
+            bg_color: bg,
+            ..CharCodeStyle::new()
+        }
+    }
+
+    const RED: TerminalCharColor = TerminalCharColor::RED;
+    const BLUE: TerminalCharColor = TerminalCharColor::Indexed(4);
+
+    #[test]
+    fn sgr_accumulates_across_sequences() {
+        assert_eq!(
+            styles(b"\x1b[1ma\x1b[31mb\x1b[4mc\x1b[44md"),
+            [
+                style(true, false, None, None),
+                style(true, false, Some(RED), None),
+                style(true, true, Some(RED), None),
+                style(true, true, Some(RED), Some(BLUE)),
+            ]
+        );
+    }
+
+    #[test]
+    fn sgr_combined_parameters() {
+        assert_eq!(
+            styles(b"\x1b[1;4;31;44ma"),
+            [style(true, true, Some(RED), Some(BLUE))]
+        );
+        // 同一序列中后出现的颜色覆盖先出现的
+        assert_eq!(styles(b"\x1b[31;34ma"), [style(false, false, Some(BLUE), None)]);
+    }
+
+    #[test]
+    fn sgr_reset_all() {
+        assert_eq!(
+            styles(b"\x1b[1;4;31;44ma\x1b[0mb"),
+            [
+                style(true, true, Some(RED), Some(BLUE)),
+                CharCodeStyle::new(),
+            ]
+        );
+        // 0之后的参数在重置后的样式上生效
+        assert_eq!(
+            styles(b"\x1b[1;4m\x1b[0;31ma"),
+            [style(false, false, Some(RED), None)]
+        );
+    }
+
+    #[test]
+    fn sgr_empty_parameters_reset() {
+        assert_eq!(
+            styles(b"\x1b[1;31ma\x1b[mb"),
+            [style(true, false, Some(RED), None), CharCodeStyle::new()]
+        );
+        assert_eq!(
+            styles(b"\x1b[4;44m\x1b[;1ma"),
+            [style(true, false, None, None)]
+        );
+    }
+
+    #[test]
+    fn sgr_resets_single_attributes() {
+        let all = b"\x1b[1;4;31;44m";
+        let cases: [(&[u8], CharCodeStyle); 4] = [
+            (b"\x1b[22m", style(false, true, Some(RED), Some(BLUE))),
+            (b"\x1b[24m", style(true, false, Some(RED), Some(BLUE))),
+            (b"\x1b[39m", style(true, true, None, Some(BLUE))),
+            (b"\x1b[49m", style(true, true, Some(RED), None)),
+        ];
+        for (reset, expected) in cases {
+            let input = [all.as_slice(), reset, b"a"].concat();
+            assert_eq!(styles(&input), [expected]);
+        }
+    }
+
+    #[test]
+    fn sgr_unknown_parameters_keep_style() {
+        assert_eq!(
+            styles(b"\x1b[31m\x1b[99;50ma"),
+            [style(false, false, Some(RED), None)]
+        );
+    }
+
+    /// 只关心颜色时的样式
+    fn colors(fg: Option<TerminalCharColor>, bg: Option<TerminalCharColor>) -> CharCodeStyle {
+        style(false, false, fg, bg)
+    }
+
+    #[test]
+    fn sgr_bright_colors() {
+        assert_eq!(
+            styles(b"\x1b[91;104ma\x1b[97;100mb"),
+            [
+                colors(Some(TerminalCharColor::Indexed(9)), Some(TerminalCharColor::Indexed(12))),
+                colors(Some(TerminalCharColor::Indexed(15)), Some(TerminalCharColor::Indexed(8))),
+            ]
+        );
+    }
+
+    #[test]
+    fn sgr_256_colors() {
+        let expected = colors(
+            Some(TerminalCharColor::Indexed(196)),
+            Some(TerminalCharColor::Indexed(21)),
+        );
+        assert_eq!(styles(b"\x1b[38;5;196;48;5;21ma"), [expected]);
+        assert_eq!(styles(b"\x1b[38:5:196;48:5:21ma"), [expected]);
+    }
+
+    #[test]
+    fn sgr_truecolor() {
+        let expected = colors(
+            Some(TerminalCharColor::Rgb(255, 128, 0)),
+            Some(TerminalCharColor::Rgb(1, 2, 3)),
+        );
+        assert_eq!(styles(b"\x1b[38;2;255;128;0;48;2;1;2;3ma"), [expected]);
+        assert_eq!(styles(b"\x1b[38:2:255:128:0;48:2:1:2:3ma"), [expected]);
+        // 带有色彩空间标识（可以为空）的冒号形式
+        assert_eq!(styles(b"\x1b[38:2::255:128:0;48:2:0:1:2:3ma"), [expected]);
+    }
+
+    #[test]
+    fn sgr_extended_color_followed_by_attributes() {
+        assert_eq!(
+            styles(b"\x1b[38;5;2;1;4ma"),
+            [style(true, true, Some(TerminalCharColor::Indexed(2)), None)]
+        );
+        assert_eq!(
+            styles(b"\x1b[1;48;2;10;20;30;31ma"),
+            [style(true, false, Some(RED), Some(TerminalCharColor::Rgb(10, 20, 30)))]
+        );
+    }
+
+    #[test]
+    fn sgr_incomplete_extended_color_is_ignored() {
+        assert_eq!(styles(b"\x1b[31m\x1b[38;5ma"), [colors(Some(RED), None)]);
+        assert_eq!(styles(b"\x1b[31m\x1b[38;2;1;2ma"), [colors(Some(RED), None)]);
+        assert_eq!(styles(b"\x1b[31m\x1b[38:5ma"), [colors(Some(RED), None)]);
+        assert_eq!(styles(b"\x1b[31m\x1b[38ma"), [colors(Some(RED), None)]);
+    }
+
+    #[test]
+    fn indexed_colors_follow_xterm_palette() {
+        let theme = Theme::default();
+        let rgba = |idx| TerminalCharColor::Indexed(idx).get_rgba(&theme);
+        assert_eq!(rgba(9), [255, 0, 0, 255]);
+        assert_eq!(rgba(16), [0, 0, 0, 255]);
+        assert_eq!(rgba(196), [255, 0, 0, 255]);
+        assert_eq!(rgba(110), [135, 175, 215, 255]);
+        assert_eq!(rgba(232), [8, 8, 8, 255]);
+        assert_eq!(rgba(255), [238, 238, 238, 255]);
+    }
+
+    #[test]
+    fn theme_defines_only_ansi_colors() {
+        let mut theme = Theme::default();
+        theme.ansi[1].0 = [1, 2, 3];
+        assert_eq!(TerminalCharColor::RED.get_rgba(&theme), [1, 2, 3, 255]);
+        assert_eq!(TerminalCharColor::Indexed(196).get_rgba(&theme), [255, 0, 0, 255]);
+        assert_eq!(TerminalCharColor::Rgb(4, 5, 6).get_rgba(&theme), [4, 5, 6, 255]);
+    }
+
+    #[test]
+    fn sgr_attributes_on_and_off() {
+        let plain = CharCodeStyle::new();
+        let cases = [
+            (2, 22, CharCodeStyle { faint: true, ..plain }),
+            (3, 23, CharCodeStyle { italic: true, ..plain }),
+            (5, 25, CharCodeStyle { blink: true, ..plain }),
+            (6, 25, CharCodeStyle { blink: true, ..plain }),
+            (7, 27, CharCodeStyle { reverse: true, ..plain }),
+            (8, 28, CharCodeStyle { conceal: true, ..plain }),
+            (9, 29, CharCodeStyle { strikethrough: true, ..plain }),
+        ];
+        for (on, off, expected) in cases {
+            let input = format!("\x1b[{}ma\x1b[{}mb", on, off);
+            assert_eq!(styles(input.as_bytes()), [expected, plain]);
+        }
+    }
+
+    #[test]
+    fn sgr_22_clears_bold_and_faint() {
+        let styles = styles(b"\x1b[1;2;3ma\x1b[22mb");
+        assert!(styles[0].bold && styles[0].faint);
+        assert!(!styles[1].bold && !styles[1].faint && styles[1].italic);
+    }
+
+    #[test]
+    fn sgr_underline_styles() {
+        let underlines = |input: &[u8]| -> Vec<Underline> {
+            styles(input).iter().map(|s| s.underline).collect()
+        };
+        assert_eq!(
+            underlines(b"\x1b[4ma\x1b[21mb\x1b[4mc\x1b[24md"),
+            [
+                Underline::Single,
+                Underline::Double,
+                Underline::Single,
+                Underline::None,
+            ]
+        );
+        assert_eq!(
+            underlines(b"\x1b[4:2ma\x1b[4:3mb\x1b[4:0mc"),
+            [Underline::Double, Underline::Single, Underline::None]
+        );
+    }
+
+    #[test]
+    fn sgr_reset_clears_all_attributes() {
+        assert_eq!(
+            styles(b"\x1b[1;2;3;4;5;7;8;9;31;44m\x1b[0ma"),
+            [CharCodeStyle::new()]
+        );
+    }
+
+    #[test]
+    fn sgr_applies_only_to_following_chars() {
+        assert_eq!(
+            styles(b"a\x1b[31mb\x1b[39mc"),
+            [
+                CharCodeStyle::new(),
+                style(false, false, Some(RED), None),
+                CharCodeStyle::new(),
+            ]
+        );
+    }
+
+    #[test]
+    fn private_modes_are_ignored() {
+        let resolver = resolve(80, 24, b"a\x1b[?25lb\x1b[?1049hc");
+        assert_eq!(lines(&resolver), ["abc"]);
+    }
+}
@@ -1,113 +1,462 @@
-use ggez::context::Has;
-use ggez::glam::Vec2;
+use ggez::glam::{Mat4, Vec2, Vec4};
 use ggez::graphics::{
-    Canvas, Color, DrawParam, Drawable, Mesh, PxScale, Quad, Rect, Text, TextFragment, TextLayout,
+    Canvas, Color, DrawParam, Mesh, PxScale, Quad, Rect, Text, TextFragment, TextLayout,
 };
 use ggez::mint::Point2;
 use ggez::Context;
 
+use crate::config::{ScreenConfig, Theme};
 use crate::constants::{
-    CHAR_CELL_HEIGHT, CHAR_CELL_THIN_WIDTH, CHAR_CELL_WIDE_WIDTH, FONT_FLAG_NAME,
+    CHAR_CELL_HEIGHT, CHAR_CELL_THIN_WIDTH, FONT_BOLD_FLAG_NAME, FONT_FLAG_NAME,
 };
-use crate::screen::char_line::{CharCode, TerminalCharColor};
+use crate::net::options::naws::WindowSize;
+use crate::screen::char_line::{CharCode, CharCodeStyle, CharLine, TerminalCharColor, Underline};
 use crate::screen::char_resolver::CharResolver;
-use crate::utils::is_wide_char;
+use crate::screen::selection::{ClickCounter, Selection, TextPos};
+use crate::screen::wrap::{char_width, RowPos, ScreenRow, Wrapper};
 
 mod char_line;
 mod char_resolver;
+mod selection;
+mod wrap;
+
+/// 闪烁字符显示与隐藏的切换间隔（毫秒）
+const BLINK_INTERVAL_MS: u128 = 500;
+/// 斜体字符的倾斜程度，即水平偏移与高度之比
+const ITALIC_SHEAR: f32 = 0.2;
 
 pub struct Screen {
     bounds: Rect,
     rows: u32,
     vt_parser: vte::Parser,
     char_resolver: CharResolver,
+    /// 是否将最新的提示符固定显示在屏幕底部
+    fixed_prompt: bool,
+    /// 固定显示的最新提示符
+    prompt_line: Option<CharLine>,
+    /// 是否以亮色代替粗体字体显示粗体
+    bold_is_bright: bool,
+    /// 是否有粗体字体可用，没有时通过错位重复绘制来模拟粗体
+    bold_font: bool,
+    /// 回滚查看时屏幕首行的位置，其中的行号计入已丢弃的行，为None时跟随最新的输出
+    scroll_top: Option<RowPos>,
+    /// 回滚查看时屏幕下方实时输出区域所占的比例
+    scrollback_split: f32,
+    /// 自动换行产生的后续行缩进的字符数
+    wrap_indent: usize,
+    /// 鼠标选中的文本
+    selection: Option<Selection>,
+    /// 是否正在拖动鼠标选择文本
+    selecting: bool,
+    click_counter: ClickCounter,
 }
 
 impl Screen {
-    pub fn new(bounds: Rect) -> Self {
-        Self {
+    pub fn new(bounds: Rect, config: &ScreenConfig, bold_font: bool) -> Self {
+        let mut screen = Self {
             bounds,
-            rows: (bounds.h / CHAR_CELL_HEIGHT).floor() as u32,
+            rows: 0,
             vt_parser: vte::Parser::new(),
             char_resolver: CharResolver::new(),
-        }
+            fixed_prompt: config.fixed_prompt,
+            prompt_line: None,
+            bold_is_bright: config.bold_is_bright,
+            bold_font,
+            scroll_top: None,
+            scrollback_split: config.scrollback_split.clamp(0., 1.),
+            wrap_indent: config.wrap_indent,
+            selection: None,
+            selecting: false,
+            click_counter: ClickCounter::new(),
+        };
+        screen
+            .char_resolver
+            .set_scrollback_limit(config.scrollback_lines);
+        screen.update_bounds(bounds);
+        screen
     }
 
-    pub fn load_buf(&mut self, buf: Box<[u8]>) {
-        for byte in buf.iter() {
+    /// 加载已解码的文本，其中可能包含ANSI转义序列
+    pub fn load_text(&mut self, text: &str) {
+        for byte in text.as_bytes() {
             self.vt_parser.advance(&mut self.char_resolver, *byte);
         }
     }
 
+    /// 服务器标记了提示符的结束，当前行即为提示符
+    pub fn mark_prompt(&mut self) {
+        if !self.fixed_prompt {
+            self.char_resolver.mark_prompt();
+        } else if let Some(line) = self.char_resolver.take_prompt_line() {
+            self.prompt_line = Some(line);
+        }
+    }
+
+    /// 本地回显用户输入的一行，回显紧跟在提示符之后
+    pub fn echo_input(&mut self, line: &str) {
+        self.char_resolver.continue_prompt_line();
+        self.load_text(&format!("{}\r\n", line));
+    }
+
+    /// 取出自上次调用以来服务器是否发送过响铃字符
+    pub fn take_bell(&mut self) -> bool {
+        self.char_resolver.take_bell()
+    }
+
+    /// 在屏幕上输出一行客户端提示信息
+    pub fn print_info(&mut self, text: &str) {
+        self.print_client_line(text, TerminalCharColor::CYAN);
+    }
+
+    /// 在屏幕上输出一行客户端错误信息
+    pub fn print_error(&mut self, text: &str) {
+        self.print_client_line(text, TerminalCharColor::RED);
+    }
+
+    fn print_client_line(&mut self, text: &str, fg_color: TerminalCharColor) {
+        let style = CharCodeStyle {
+            fg_color: Some(fg_color),
+            ..CharCodeStyle::new()
+        };
+        self.char_resolver.push_client_line(text, style);
+    }
+
     pub fn update_bounds(&mut self, bounds: Rect) {
         self.bounds = bounds;
         self.rows = (bounds.h / CHAR_CELL_HEIGHT).floor() as u32;
+        let size = self.window_size();
+        self.char_resolver
+            .set_size(size.columns as usize, size.rows as usize);
     }
 
-    pub fn draw(&self, canvas: &mut Canvas, ctx: &Context) {
-        let lines = self.char_resolver.get_lines();
-        let line_len = lines.len();
-        if line_len == 0 {
-            return;
+    /// 屏幕以字符为单位的大小，列数按照非宽体字符的宽度计算
+    pub fn window_size(&self) -> WindowSize {
+        WindowSize {
+            columns: (self.bounds.w / CHAR_CELL_THIN_WIDTH).floor() as u16,
+            rows: self.rows as u16,
         }
-        let start_line_idx = if line_len > self.rows as usize {
-            line_len - self.rows as usize
+    }
+
+    /// 向上（rows为正）或向下滚动rows个屏幕行，滚动到底部后恢复跟随最新的输出
+    pub fn scroll(&mut self, rows: isize) {
+        let lines = self.char_resolver.get_lines();
+        let top = self
+            .wrapper()
+            .offset(lines, self.view_top(), rows.saturating_neg());
+        self.scroll_top = if top < self.bottom_top() {
+            Some((top.0 + self.char_resolver.dropped_lines(), top.1))
         } else {
+            None
+        };
+    }
+
+    /// 回到底部，跟随最新的输出
+    pub fn scroll_to_bottom(&mut self) {
+        self.scroll_top = None;
+    }
+
+    /// 翻页时滚动的屏幕行数，保留一行上下文。
+    /// 按照回滚查看时回滚区域的大小计算，从底部开始翻页时屏幕随之分屏
+    pub fn page_rows(&self) -> isize {
+        self.scrollback_rows().saturating_sub(1).max(1) as isize
+    }
+
+    /// 屏幕下方实时输出区域的行数，未回滚查看或未启用分屏时为0
+    fn tail_rows(&self) -> usize {
+        if self.scroll_top.is_none() {
             0
+        } else {
+            self.split_rows()
+        }
+    }
+
+    /// 回滚查看时屏幕下方实时输出区域的行数
+    fn split_rows(&self) -> usize {
+        let rows = self.view_rows();
+        let split_rows = (rows as f32 * self.scrollback_split).round() as usize;
+        // 回滚区域至少保留两行，其中一行用于提示下方还有多少行
+        split_rows.min(rows.saturating_sub(2))
+    }
+
+    /// 回滚查看时上方回滚区域中显示输出的行数，区域的最后一行留给下方行数的提示
+    fn scrollback_rows(&self) -> usize {
+        (self.view_rows() - self.split_rows()).saturating_sub(1)
+    }
+
+    /// 用于显示输出的行数，固定显示的提示符占据屏幕的最后一行
+    fn view_rows(&self) -> usize {
+        if self.fixed_prompt && self.prompt_line.is_some() {
+            self.rows.saturating_sub(1) as usize
+        } else {
+            self.rows as usize
+        }
+    }
+
+    /// 按照当前的屏幕宽度自动换行，窗口大小变化后随之重新换行
+    fn wrapper(&self) -> Wrapper {
+        Wrapper::new(
+            self.bounds.w,
+            self.wrap_indent as f32 * CHAR_CELL_THIN_WIDTH,
+        )
+    }
+
+    /// 跟随最新的输出时屏幕首行的位置
+    fn bottom_top(&self) -> RowPos {
+        self.wrapper()
+            .top_of_end(self.char_resolver.get_lines(), self.view_rows())
+    }
+
+    /// 回滚查看时屏幕首行的位置。
+    /// 回滚的位置以字符行及行内的屏幕行记录，即使有新的输出，屏幕上的内容也保持不动；
+    /// 首行所在的字符行被丢弃后停留在最早的一行，重新换行后行内的屏幕行数不足时停留在该行的最后一个屏幕行
+    fn view_top(&self) -> RowPos {
+        let bottom = self.bottom_top();
+        let Some((line_no, row)) = self.scroll_top else {
+            return bottom;
         };
-        let end_line_idx = line_len - 1;
-        for line_idx in start_line_idx..=end_line_idx {
-            let renderable_line = &lines[line_idx];
-            let char_codes = renderable_line.char_codes();
-            let char_code_len = char_codes.len();
-            if char_code_len == 0 {
-                continue;
+        let top = match line_no.checked_sub(self.char_resolver.dropped_lines()) {
+            Some(line_idx) => {
+                self.wrapper()
+                    .offset(self.char_resolver.get_lines(), (line_idx, row), 0)
             }
-            // line_idx是一个大于等于0的，表示文本行列表的索引，
-            // 这里将其减去 start_line_idx，才能得到屏幕上的垂直cell的索引
-            let row_idx = line_idx - start_line_idx;
-            // 已经渲染的字符的总宽度，
-            let mut rendered_char_width = 0f32;
-            for char_idx in 0..char_code_len {
-                let cc = &char_codes[char_idx];
-                // 如果cc为宽度字符，用更宽的格子呈现
-                let char_width = if is_wide_char(cc.c) {
-                    CHAR_CELL_WIDE_WIDTH
-                } else {
-                    CHAR_CELL_THIN_WIDTH
-                };
-                if rendered_char_width + char_width > self.bounds.w {
-                    // 若该字符待渲染的宽度加上前面已经渲染的宽度超过了当前终端画布宽度，不再渲染后续内容
-                    break;
-                }
-                let rect = Rect::new(
-                    self.bounds.x + rendered_char_width,
-                    self.bounds.y + (row_idx as f32 * CHAR_CELL_HEIGHT),
-                    char_width,
-                    CHAR_CELL_HEIGHT,
-                );
-                self.draw_single_char_code(canvas, ctx, cc, rect);
-                rendered_char_width += char_width;
+            None => (0, 0),
+        };
+        top.min(bottom)
+    }
+
+    /// 屏幕上的各个屏幕行及其在屏幕上的行号，回滚查看时包括上方的回滚区域与下方的实时输出区域
+    fn layout(&self) -> Vec<(usize, ScreenRow<'_>)> {
+        let rows = self.view_rows();
+        let lines = self.char_resolver.get_lines();
+        let wrapper = self.wrapper();
+        if self.scroll_top.is_none() {
+            return wrapper.rows_to_end(lines, rows).into_iter().enumerate().collect();
+        }
+        let tail_rows = self.tail_rows();
+        let mut layout: Vec<_> = wrapper
+            .rows_from(lines, self.view_top(), self.scrollback_rows())
+            .into_iter()
+            .enumerate()
+            .collect();
+        let tail = wrapper.rows_to_end(lines, tail_rows);
+        layout.extend(
+            tail.into_iter()
+                .enumerate()
+                .map(|(row_idx, row)| (rows - tail_rows + row_idx, row)),
+        );
+        layout
+    }
+
+    /// 屏幕上(x, y)处的字符位置。位于行尾之后时为该行最后一个字符之后的位置，
+    /// 位于屏幕之外时取最近的屏幕行，屏幕上没有内容时为None
+    fn text_pos_at(&self, x: f32, y: f32) -> Option<TextPos> {
+        let layout = self.layout();
+        let row_idx = ((y - self.bounds.y) / CHAR_CELL_HEIGHT).floor().max(0.) as usize;
+        let (_, row) = layout
+            .iter()
+            .rev()
+            .find(|(idx, _)| *idx <= row_idx)
+            .or(layout.first())?;
+        let mut right = self.bounds.x;
+        if row.continuation {
+            right += self.wrapper().indent();
+        }
+        let mut col = row.start;
+        for cc in row.char_codes {
+            right += char_width(cc.c);
+            if x < right {
+                break;
             }
+            col += 1;
+        }
+        Some((self.char_resolver.dropped_lines() + row.line_idx, col))
+    }
+
+    /// 按下鼠标左键，开始选择文本。连续点击两次选中单词，三次选中整行，
+    /// 在屏幕之外按下时保留原有的选择
+    pub fn mouse_down(&mut self, x: f32, y: f32) {
+        if !self.bounds.contains(Point2::from([x, y])) {
+            return;
         }
+        self.selection = self.text_pos_at(x, y).map(|pos| {
+            let unit = self.click_counter.click(pos);
+            Selection::new(unit, pos)
+        });
+        self.selecting = self.selection.is_some();
+    }
+
+    /// 拖动鼠标，扩展选中的范围
+    pub fn mouse_move(&mut self, x: f32, y: f32) {
+        if !self.selecting {
+            return;
+        }
+        if let Some(pos) = self.text_pos_at(x, y) {
+            if let Some(selection) = self.selection.as_mut() {
+                selection.extend(pos);
+            }
+        }
+    }
+
+    /// 松开鼠标左键，结束选择
+    pub fn mouse_up(&mut self) {
+        self.selecting = false;
+    }
+
+    /// 选中的第一个与最后一个字符的位置
+    fn selection_range(&self) -> Option<(TextPos, TextPos)> {
+        self.selection.as_ref().and_then(|selection| {
+            selection.range(
+                self.char_resolver.get_lines(),
+                self.char_resolver.dropped_lines(),
+            )
+        })
+    }
+
+    /// 选中的文本，ansi为true时以SGR序列保留颜色等样式。没有选中任何内容时为None
+    pub fn selected_text(&self, ansi: bool) -> Option<String> {
+        let range = self.selection_range()?;
+        Some(selection::selected_text(
+            self.char_resolver.get_lines(),
+            self.char_resolver.dropped_lines(),
+            range,
+            ansi,
+        ))
+    }
+
+    pub fn draw(&self, canvas: &mut Canvas, ctx: &Context, theme: &Theme) {
+        let rows = self.view_rows();
+        let indent = self.wrapper().indent();
+        // 回滚查看时屏幕分为上方停留在回滚位置的区域和下方的实时输出区域
+        let tail_rows = self.tail_rows();
+        let scrollback_rows = self.scrollback_rows();
+        let selection = self.selection_range();
+        let layout = self.layout();
+        for (row_idx, row) in &layout {
+            self.draw_row(canvas, ctx, theme, row, indent, *row_idx, selection);
+        }
+        if tail_rows > 0 {
+            self.draw_split_line(canvas, theme, rows - tail_rows);
+        }
+        if let Some(prompt_line) = self.prompt_line.as_ref().filter(|_| self.fixed_prompt) {
+            // 固定显示的提示符只占一行，超出屏幕宽度的部分不显示
+            let row = ScreenRow {
+                line_idx: 0,
+                start: 0,
+                char_codes: prompt_line.char_codes(),
+                continuation: false,
+            };
+            self.draw_row(canvas, ctx, theme, &row, 0., rows, None);
+        }
+        if self.scroll_top.is_some() {
+            let lines = self.char_resolver.get_lines();
+            // 回滚区域中最后一个字符行只显示了一部分时，同样计入下方的行数
+            let shown = layout
+                .iter()
+                .take_while(|(row_idx, _)| *row_idx < scrollback_rows)
+                .last()
+                .map_or(0, |(_, row)| {
+                    let line_len = lines[row.line_idx].char_codes().len();
+                    if row.start + row.char_codes.len() < line_len {
+                        row.line_idx
+                    } else {
+                        row.line_idx + 1
+                    }
+                });
+            let below = lines.len().saturating_sub(shown);
+            if below > 0 {
+                self.draw_more_below(canvas, theme, below, scrollback_rows);
+            }
+        }
+    }
+
+    /// 在第row行的顶部绘制回滚区域与实时输出区域的分隔线
+    fn draw_split_line(&self, canvas: &mut Canvas, theme: &Theme, row: usize) {
+        canvas.draw(
+            &Quad,
+            DrawParam::default()
+                .dest([self.bounds.x, self.bounds.y + row as f32 * CHAR_CELL_HEIGHT - 1.])
+                .scale([self.bounds.w, 2.])
+                .color(Color::from(theme.input_foreground)),
+        );
     }
 
-    fn draw_single_char_code(&self, canvas: &mut Canvas, ctx: &Context, cc: &CharCode, rect: Rect) {
-        let fg_color = convert_color(&cc.style.fg_color);
+    /// 回滚查看时在回滚区域预留的第row行提示下方还有多少行
+    fn draw_more_below(&self, canvas: &mut Canvas, theme: &Theme, below: usize, row: usize) {
+        let rect = Rect::new(
+            self.bounds.x,
+            self.bounds.y + (row as f32 * CHAR_CELL_HEIGHT),
+            self.bounds.w,
+            CHAR_CELL_HEIGHT,
+        );
+        canvas.draw(
+            &Quad,
+            DrawParam::default()
+                .dest(rect.point())
+                .scale(rect.size())
+                .color(Color::from(theme.input_inactive)),
+        );
         let mut txt = Text::new(TextFragment {
-            text: cc.c.clone().to_string(),
+            text: format!("↓ 下方还有{}行（Shift+End回到底部）", below),
             font: Some(FONT_FLAG_NAME.into()),
-            scale: Some(PxScale {
-                x: rect.w,
-                y: rect.h,
-            }),
-            color: Some(fg_color),
+            scale: Some(PxScale::from(CHAR_CELL_HEIGHT - 6.)),
+            color: Some(theme.background.into()),
         });
-        txt.set_bounds(Vec2::new(rect.w, rect.h))
-            .set_layout(TextLayout::center());
+        txt.set_bounds(rect.size()).set_layout(TextLayout::center());
+        canvas.draw(&txt, DrawParam::default().dest(rect.center()));
+    }
+
+    /// 在屏幕的第row_idx行绘制一个屏幕行，自动换行产生的后续行缩进indent，
+    /// 位于selection范围内的字符以反显突出显示
+    #[allow(clippy::too_many_arguments)]
+    fn draw_row(
+        &self,
+        canvas: &mut Canvas,
+        ctx: &Context,
+        theme: &Theme,
+        row: &ScreenRow,
+        indent: f32,
+        row_idx: usize,
+        selection: Option<(TextPos, TextPos)>,
+    ) {
+        let line_no = self.char_resolver.dropped_lines() + row.line_idx;
+        // 已经渲染的字符的总宽度，
+        let mut rendered_char_width = if row.continuation { indent } else { 0. };
+        for (idx, cc) in row.char_codes.iter().enumerate() {
+            let char_width = char_width(cc.c);
+            if rendered_char_width + char_width > self.bounds.w {
+                // 若该字符待渲染的宽度加上前面已经渲染的宽度超过了当前终端画布宽度，不再渲染后续内容
+                break;
+            }
+            let rect = Rect::new(
+                self.bounds.x + rendered_char_width,
+                self.bounds.y + (row_idx as f32 * CHAR_CELL_HEIGHT),
+                char_width,
+                CHAR_CELL_HEIGHT,
+            );
+            let pos = (line_no, row.start + idx);
+            if selection.is_some_and(|(start, end)| start <= pos && pos <= end) {
+                let mut cc = *cc;
+                cc.style.reverse = !cc.style.reverse;
+                self.draw_single_char_code(canvas, ctx, theme, &cc, rect);
+            } else {
+                self.draw_single_char_code(canvas, ctx, theme, cc, rect);
+            }
+            rendered_char_width += char_width;
+        }
+    }
+
+    fn draw_single_char_code(
+        &self,
+        canvas: &mut Canvas,
+        ctx: &Context,
+        theme: &Theme,
+        cc: &CharCode,
+        rect: Rect,
+    ) {
+        let style = &cc.style;
+        let (fg_color, bg_color) = self.cell_colors(style, theme);
         // 背景色
-        if let Some(bg_color) = &cc.style.bg_color {
-            let bg_color = convert_color(bg_color);
+        if let Some(bg_color) = bg_color {
             canvas.draw(
                 &Quad,
                 DrawParam::default()
@@ -116,18 +465,299 @@ impl Screen {
                     .color(bg_color),
             );
         }
-        // 下划线
-        if cc.style.underline {
-            let start = Point2::from([rect.x, rect.y + rect.h]);
-            let end = Point2::from([rect.x + rect.w, rect.y + rect.h]);
-            let mesh = Mesh::new_line(ctx, &[start, end], 1., fg_color).expect("");
-            canvas.draw(&mesh, DrawParam::default());
+        // 隐藏的字符以及处于隐藏阶段的闪烁字符只绘制背景
+        let blink_hidden = style.blink
+            && ctx.time.time_since_start().as_millis() / BLINK_INTERVAL_MS % 2 == 1;
+        if style.conceal || blink_hidden {
+            return;
         }
-        canvas.draw(&txt, DrawParam::default().dest(rect.center()));
+        // 下划线与删除线
+        let bottom = rect.y + rect.h - 1.;
+        match style.underline {
+            Underline::None => {}
+            Underline::Single => draw_horizontal_line(canvas, ctx, rect, bottom, fg_color),
+            Underline::Double => {
+                draw_horizontal_line(canvas, ctx, rect, bottom, fg_color);
+                draw_horizontal_line(canvas, ctx, rect, bottom - 3., fg_color);
+            }
+        }
+        if style.strikethrough {
+            draw_horizontal_line(canvas, ctx, rect, rect.y + rect.h / 2., fg_color);
+        }
+        // 亮色代替粗体时不再使用粗体字体
+        let bold = style.bold && !self.bold_is_bright;
+        let font = if bold && self.bold_font {
+            FONT_BOLD_FLAG_NAME
+        } else {
+            FONT_FLAG_NAME
+        };
+        let mut txt = Text::new(TextFragment {
+            text: cc.c.to_string(),
+            font: Some(font.into()),
+            scale: Some(PxScale {
+                x: rect.w,
+                y: rect.h,
+            }),
+            color: Some(fg_color),
+        });
+        txt.set_bounds(Vec2::new(rect.w, rect.h))
+            .set_layout(TextLayout::center());
+        let center = rect.center();
+        canvas.draw(&txt, text_param(center.x, center.y, style.italic));
+        if bold && !self.bold_font {
+            canvas.draw(&txt, text_param(center.x + 1., center.y, style.italic));
+        }
+    }
+
+    /// 字符实际绘制的前景色与背景色，背景色为None时不绘制背景
+    fn cell_colors(&self, style: &CharCodeStyle, theme: &Theme) -> (Color, Option<Color>) {
+        let fg = match style.fg_color {
+            Some(TerminalCharColor::Indexed(idx @ 0..=7)) if style.bold && self.bold_is_bright => {
+                Some(TerminalCharColor::Indexed(idx + 8))
+            }
+            fg => fg,
+        };
+        let mut fg = fg.map_or(theme.foreground.into(), |color| convert_color(&color, theme));
+        let mut bg = style.bg_color.map(|color| convert_color(&color, theme));
+        if style.reverse {
+            let reversed_fg = bg.unwrap_or(theme.background.into());
+            bg = Some(fg);
+            fg = reversed_fg;
+        }
+        if style.faint {
+            fg.a = 0.5;
+        }
+        (fg, bg)
     }
 }
 
-fn convert_color(terminal_color: &TerminalCharColor) -> Color {
-    let [r, g, b, a] = terminal_color.get_rgba();
+/// 以(x, y)为中心绘制字符的参数，斜体时将字符向右倾斜
+fn text_param(x: f32, y: f32, italic: bool) -> DrawParam {
+    if !italic {
+        return DrawParam::default().dest(Point2::from([x, y]));
+    }
+    // 字符坐标以中心为原点，越靠上（y越小）向右偏移越多
+    let transform = Mat4::from_cols(
+        Vec4::X,
+        Vec4::new(-ITALIC_SHEAR, 1., 0., 0.),
+        Vec4::Z,
+        Vec4::new(x, y, 0., 1.),
+    );
+    DrawParam::default().transform(transform)
+}
+
+/// 在字符所在的格子中y处绘制一条水平线，用于下划线与删除线
+fn draw_horizontal_line(canvas: &mut Canvas, ctx: &Context, rect: Rect, y: f32, color: Color) {
+    let start = Point2::from([rect.x, y]);
+    let end = Point2::from([rect.x + rect.w, y]);
+    let mesh = Mesh::new_line(ctx, &[start, end], 1., color).expect("");
+    canvas.draw(&mesh, DrawParam::default());
+}
+
+fn convert_color(terminal_color: &TerminalCharColor, theme: &Theme) -> Color {
+    let [r, g, b, a] = terminal_color.get_rgba(theme);
     Color::from_rgba(r, g, b, a)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// rows行、columns列的屏幕，回滚缓冲区最多保留scrollback_lines行
+    fn screen(rows: usize, columns: usize, split: f32, scrollback_lines: usize) -> Screen {
+        let config = ScreenConfig {
+            scrollback_split: split,
+            scrollback_lines,
+            ..ScreenConfig::default()
+        };
+        let bounds = Rect::new(
+            0.,
+            0.,
+            columns as f32 * CHAR_CELL_THIN_WIDTH,
+            rows as f32 * CHAR_CELL_HEIGHT,
+        );
+        Screen::new(bounds, &config, true)
+    }
+
+    /// 依次输出内容为from到to的各行
+    fn print_lines(screen: &mut Screen, lines: std::ops::Range<usize>) {
+        for line in lines {
+            screen.load_text(&format!("{}\r\n", line));
+        }
+    }
+
+    /// 屏幕上各行的行号及内容
+    fn rows(screen: &Screen) -> Vec<(usize, String)> {
+        screen
+            .layout()
+            .iter()
+            .map(|(row_idx, row)| (*row_idx, row.char_codes.iter().map(|cc| cc.c).collect()))
+            .collect()
+    }
+
+    fn row_texts(screen: &Screen) -> Vec<String> {
+        rows(screen).into_iter().map(|(_, text)| text).collect()
+    }
+
+    #[test]
+    fn scroll_moves_view_and_returns_to_bottom() {
+        let mut screen = screen(5, 10, 0., 100);
+        print_lines(&mut screen, 0..20);
+        // 最后一行为光标所在的空行
+        assert_eq!(row_texts(&screen), ["16", "17", "18", "19", ""]);
+        assert_eq!(screen.view_top(), (16, 0));
+
+        screen.scroll(3);
+        assert_eq!(screen.view_top(), (13, 0));
+        // 最后一行预留给下方行数的提示
+        assert_eq!(
+            rows(&screen),
+            [
+                (0, "13".into()),
+                (1, "14".into()),
+                (2, "15".into()),
+                (3, "16".into())
+            ]
+        );
+        screen.scroll(isize::MAX);
+        assert_eq!(screen.view_top(), (0, 0));
+        assert_eq!(row_texts(&screen), ["0", "1", "2", "3"]);
+        screen.scroll(-2);
+        assert_eq!(screen.view_top(), (2, 0));
+        screen.scroll(-isize::MAX);
+        assert!(screen.scroll_top.is_none());
+        assert_eq!(screen.view_top(), (16, 0));
+    }
+
+    #[test]
+    fn more_below_row_is_not_covered() {
+        let mut screen = screen(10, 10, 0.3, 100);
+        print_lines(&mut screen, 0..30);
+        screen.scroll(10);
+        // 回滚区域显示6行，第7行为提示，下方3行为实时输出
+        let layout = rows(&screen);
+        let row_indices: Vec<usize> = layout.iter().map(|(row_idx, _)| *row_idx).collect();
+        assert_eq!(row_indices, [0, 1, 2, 3, 4, 5, 7, 8, 9]);
+        assert_eq!(layout[0].1, "11");
+        assert_eq!(layout[6].1, "28");
+        assert_eq!(screen.scrollback_rows(), 6);
+    }
+
+    #[test]
+    fn page_size_follows_split() {
+        // 依次为分屏比例、实时输出区域的行数与翻页的行数
+        for (split, tail_rows, page_lines) in [(0., 0, 8), (0.3, 3, 5), (1., 8, 1)] {
+            let mut screen = screen(10, 10, split, 100);
+            print_lines(&mut screen, 0..30);
+            assert_eq!(screen.tail_rows(), 0);
+            assert_eq!(screen.page_rows(), page_lines);
+            screen.scroll(1);
+            assert_eq!(screen.tail_rows(), tail_rows);
+            assert_eq!(screen.page_rows(), page_lines);
+        }
+    }
+
+    #[test]
+    fn first_page_up_keeps_top_row_visible() {
+        // 分屏比例为1时回滚区域只有一行，无法保留上下文
+        for split in [0., 0.3] {
+            let mut screen = screen(10, 10, split, 100);
+            print_lines(&mut screen, 0..30);
+            let top = row_texts(&screen)[0].clone();
+            screen.scroll(screen.page_rows());
+            let layout = rows(&screen);
+            let (_, last) = layout
+                .iter()
+                .rfind(|(row_idx, _)| *row_idx < screen.scrollback_rows())
+                .unwrap();
+            assert_eq!(*last, top, "split {}", split);
+        }
+    }
+
+    #[test]
+    fn scroll_steps_through_wrapped_rows() {
+        let mut screen = screen(5, 4, 0., 100);
+        print_lines(&mut screen, 0..3);
+        screen.load_text("abcdefghijkl\r\n");
+        print_lines(&mut screen, 3..6);
+        // 长行占据三个屏幕行
+        assert_eq!(row_texts(&screen), ["ijkl", "3", "4", "5", ""]);
+        assert_eq!(screen.view_top(), (3, 2));
+
+        screen.scroll(1);
+        assert_eq!(screen.view_top(), (3, 1));
+        assert_eq!(row_texts(&screen), ["efgh", "ijkl", "3", "4"]);
+        screen.scroll(2);
+        assert_eq!(screen.view_top(), (2, 0));
+        assert_eq!(row_texts(&screen), ["2", "abcd", "efgh", "ijkl"]);
+        screen.scroll(-1);
+        assert_eq!(row_texts(&screen), ["abcd", "efgh", "ijkl", "3"]);
+        // 回到跟随最新输出时的位置后不再回滚查看
+        screen.scroll(-2);
+        assert!(screen.scroll_top.is_none());
+        assert_eq!(row_texts(&screen), ["ijkl", "3", "4", "5", ""]);
+    }
+
+    #[test]
+    fn line_taller_than_pane_is_scrollable() {
+        // 回滚区域只有两行，长行占据六个屏幕行
+        let mut screen = screen(3, 4, 0., 100);
+        screen.load_text("0\r\nabcdefghijklmnopqrstuvwx\r\n1\r\n");
+        screen.scroll(isize::MAX);
+        assert_eq!(row_texts(&screen), ["0", "abcd"]);
+        let mut pages = Vec::new();
+        for _ in 0..6 {
+            screen.scroll(-screen.page_rows());
+            pages.push(row_texts(&screen));
+        }
+        assert_eq!(
+            pages,
+            [
+                vec!["abcd", "efgh"],
+                vec!["efgh", "ijkl"],
+                vec!["ijkl", "mnop"],
+                vec!["mnop", "qrst"],
+                vec!["qrst", "uvwx"],
+                vec!["uvwx", "1", ""],
+            ]
+        );
+    }
+
+    #[test]
+    fn scroll_position_survives_rewrap() {
+        let mut screen = screen(5, 4, 0., 100);
+        screen.load_text("abcdefghijkl\r\n");
+        print_lines(&mut screen, 0..6);
+        screen.scroll(isize::MAX);
+        screen.scroll(-2);
+        assert_eq!(screen.view_top(), (0, 2));
+        // 窗口变宽后长行只占两个屏幕行，停留在该行的最后一个屏幕行
+        let bounds = Rect::new(0., 0., 8. * CHAR_CELL_THIN_WIDTH, 5. * CHAR_CELL_HEIGHT);
+        screen.update_bounds(bounds);
+        assert_eq!(screen.view_top(), (0, 1));
+        assert_eq!(row_texts(&screen), ["ijkl", "0", "1", "2"]);
+        screen.scroll(1);
+        assert_eq!(row_texts(&screen), ["abcdefgh", "ijkl", "0", "1"]);
+    }
+
+    #[test]
+    fn scrolled_view_is_stable_while_lines_are_dropped() {
+        // 回滚缓冲区最多保留屏幕的5行以及之外的20行
+        let mut screen = screen(5, 10, 0., 20);
+        print_lines(&mut screen, 0..40);
+        assert_eq!(screen.char_resolver.dropped_lines(), 16);
+        screen.scroll(10);
+        let before = row_texts(&screen);
+        assert_eq!(before, ["26", "27", "28", "29"]);
+
+        print_lines(&mut screen, 40..45);
+        assert_eq!(screen.char_resolver.dropped_lines(), 21);
+        assert_eq!(row_texts(&screen), before);
+
+        // 查看的行被丢弃后停留在最早的一行
+        print_lines(&mut screen, 45..60);
+        assert_eq!(screen.char_resolver.dropped_lines(), 36);
+        assert_eq!(screen.view_top(), (0, 0));
+        assert_eq!(row_texts(&screen), ["36", "37", "38", "39"]);
+    }
+}
@@ -0,0 +1,277 @@
+use std::time::{Duration, Instant};
+
+use crate::screen::char_line::{CharCodeStyle, CharLine};
+
+/// 连续点击的最大间隔，超过后重新计数
+const MULTI_CLICK_INTERVAL: Duration = Duration::from_millis(500);
+
+/// 字符在回滚缓冲区中的位置：行号（含已丢弃的行）以及字符在行中的下标。
+/// 行号计入已丢弃的行，因此有新的输出时选中的内容保持不变
+pub type TextPos = (usize, usize);
+
+/// 选择的单位，分别对应单击拖动、双击与三击
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum SelectionUnit {
+    Char,
+    Word,
+    Line,
+}
+
+/// 鼠标选中的文本
+pub struct Selection {
+    unit: SelectionUnit,
+    /// 按下鼠标的位置
+    anchor: TextPos,
+    /// 拖动时鼠标当前的位置
+    head: TextPos,
+}
+
+impl Selection {
+    pub fn new(unit: SelectionUnit, pos: TextPos) -> Self {
+        Self {
+            unit,
+            anchor: pos,
+            head: pos,
+        }
+    }
+
+    pub fn extend(&mut self, pos: TextPos) {
+        self.head = pos;
+    }
+
+    /// 选中的第一个与最后一个字符的位置（均包含在内），按选择的单位扩展到整个单词或整行。
+    /// lines为回滚缓冲区中的所有行，dropped为已丢弃的行数。
+    /// 单击而没有拖动时没有选中任何内容
+    pub fn range(&self, lines: &[CharLine], dropped: usize) -> Option<(TextPos, TextPos)> {
+        let (start, end) = if self.anchor <= self.head {
+            (self.anchor, self.head)
+        } else {
+            (self.head, self.anchor)
+        };
+        match self.unit {
+            SelectionUnit::Char if start == end => None,
+            SelectionUnit::Char => Some((start, end)),
+            SelectionUnit::Word => {
+                let line = |pos: TextPos| pos.0.checked_sub(dropped).and_then(|idx| lines.get(idx));
+                let start_col = line(start).map_or(start.1, |line| word_bounds(line, start.1).0);
+                let end_col = line(end).map_or(end.1, |line| word_bounds(line, end.1).1);
+                Some(((start.0, start_col), (end.0, end_col)))
+            }
+            SelectionUnit::Line => Some(((start.0, 0), (end.0, usize::MAX))),
+        }
+    }
+}
+
+/// 单词的第一个与最后一个字符的下标。连续的字母数字（包括汉字）构成单词，
+/// 连续的空白或标点也各自视为一个整体
+fn word_bounds(line: &CharLine, col: usize) -> (usize, usize) {
+    let chars: Vec<char> = line.char_codes().iter().map(|cc| cc.c).collect();
+    let Some(&c) = chars.get(col) else {
+        return (col, col);
+    };
+    let class = char_class(c);
+    let start = chars[..col]
+        .iter()
+        .rposition(|c| char_class(*c) != class)
+        .map_or(0, |idx| idx + 1);
+    let end = chars[col..]
+        .iter()
+        .position(|c| char_class(*c) != class)
+        .map_or(chars.len(), |idx| col + idx)
+        - 1;
+    (start, end)
+}
+
+fn char_class(c: char) -> u8 {
+    if c.is_whitespace() {
+        0
+    } else if c.is_alphanumeric() || c == '_' {
+        1
+    } else {
+        2
+    }
+}
+
+/// 选中的文本，各行以换行分隔。
+/// ansi为true时以SGR序列保留颜色等样式，否则为纯文本，并去掉行尾的空白
+pub fn selected_text(
+    lines: &[CharLine],
+    dropped: usize,
+    (start, end): (TextPos, TextPos),
+    ansi: bool,
+) -> String {
+    let mut text_lines = Vec::new();
+    for line_no in start.0.max(dropped)..=end.0 {
+        let Some(line) = lines.get(line_no - dropped) else {
+            break;
+        };
+        let char_codes = line.char_codes();
+        let from = if line_no == start.0 { start.1 } else { 0 };
+        let to = if line_no == end.0 {
+            end.1.saturating_add(1)
+        } else {
+            usize::MAX
+        }
+        .min(char_codes.len());
+        let char_codes = char_codes.get(from..to).unwrap_or_default();
+        let mut text = String::new();
+        if ansi {
+            let mut style = CharCodeStyle::new();
+            for cc in char_codes {
+                if cc.style != style {
+                    style = cc.style;
+                    text.push_str(&style.sgr());
+                }
+                text.push(cc.c);
+            }
+            if style != CharCodeStyle::new() {
+                text.push_str("\x1b[0m");
+            }
+        } else {
+            text.extend(char_codes.iter().map(|cc| cc.c));
+            text.truncate(text.trim_end().len());
+        }
+        text_lines.push(text);
+    }
+    text_lines.join("\n")
+}
+
+/// 统计连续点击的次数，用于区分单击、双击与三击，三击之后重新开始
+pub struct ClickCounter {
+    last: Option<(Instant, TextPos)>,
+    count: usize,
+}
+
+impl ClickCounter {
+    pub fn new() -> Self {
+        Self {
+            last: None,
+            count: 0,
+        }
+    }
+
+    /// 在pos处点击，返回对应的选择单位
+    pub fn click(&mut self, pos: TextPos) -> SelectionUnit {
+        let now = Instant::now();
+        let repeated = self
+            .last
+            .is_some_and(|(at, last_pos)| last_pos == pos && now - at <= MULTI_CLICK_INTERVAL);
+        self.count = if repeated { self.count % 3 + 1 } else { 1 };
+        self.last = Some((now, pos));
+        match self.count {
+            1 => SelectionUnit::Char,
+            2 => SelectionUnit::Word,
+            _ => SelectionUnit::Line,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::screen::char_resolver::CharResolver;
+
+    fn resolve(input: &[u8]) -> CharResolver {
+        let mut resolver = CharResolver::new();
+        let mut parser = vte::Parser::new();
+        for byte in input {
+            parser.advance(&mut resolver, *byte);
+        }
+        resolver
+    }
+
+    fn text(lines: &[CharLine], selection: &Selection, ansi: bool) -> String {
+        let range = selection.range(lines, 0).unwrap();
+        selected_text(lines, 0, range, ansi)
+    }
+
+    #[test]
+    fn drag_selects_characters_across_lines() {
+        let resolver = resolve(b"hello world\nsecond line   \nthird");
+        let lines = resolver.get_lines();
+        let mut selection = Selection::new(SelectionUnit::Char, (0, 6));
+        selection.extend((2, 2));
+        assert_eq!(text(lines, &selection, false), "world\nsecond line\nthi");
+        // 反向拖动选中同样的内容
+        let mut selection = Selection::new(SelectionUnit::Char, (2, 2));
+        selection.extend((0, 6));
+        assert_eq!(text(lines, &selection, false), "world\nsecond line\nthi");
+    }
+
+    #[test]
+    fn click_without_drag_selects_nothing() {
+        let resolver = resolve(b"hello");
+        assert!(Selection::new(SelectionUnit::Char, (0, 1))
+            .range(resolver.get_lines(), 0)
+            .is_none());
+    }
+
+    #[test]
+    fn double_click_selects_words() {
+        let resolver = resolve("look at 北大侠客行, ok".as_bytes());
+        let lines = resolver.get_lines();
+        assert_eq!(
+            text(lines, &Selection::new(SelectionUnit::Word, (0, 1)), false),
+            "look"
+        );
+        assert_eq!(
+            text(lines, &Selection::new(SelectionUnit::Word, (0, 10)), false),
+            "北大侠客行"
+        );
+        let mut selection = Selection::new(SelectionUnit::Word, (0, 5));
+        selection.extend((0, 18));
+        assert_eq!(text(lines, &selection, false), "at 北大侠客行, ok");
+    }
+
+    #[test]
+    fn triple_click_selects_lines() {
+        let resolver = resolve(b"first\nsecond\nthird");
+        let lines = resolver.get_lines();
+        let mut selection = Selection::new(SelectionUnit::Line, (0, 3));
+        selection.extend((1, 0));
+        assert_eq!(text(lines, &selection, false), "first\nsecond");
+    }
+
+    #[test]
+    fn dropped_lines_are_skipped() {
+        let resolver = resolve(b"third\nfourth");
+        let lines = resolver.get_lines();
+        // 行号计入了已丢弃的两行
+        let mut selection = Selection::new(SelectionUnit::Line, (1, 0));
+        selection.extend((3, 0));
+        let range = selection.range(lines, 2).unwrap();
+        assert_eq!(selected_text(lines, 2, range, false), "third\nfourth");
+    }
+
+    #[test]
+    fn ansi_copy_round_trips_styles() {
+        let input = "a\x1b[1;31mb\x1b[4;38;5;100mc\x1b[0m d\n\x1b[7;48;2;1;2;3me".as_bytes();
+        let resolver = resolve(input);
+        let lines = resolver.get_lines();
+        let mut selection = Selection::new(SelectionUnit::Char, (0, 0));
+        selection.extend((1, 0));
+        let copied = text(lines, &selection, true);
+        let copied_lines = resolve(copied.as_bytes());
+        let styles = |resolver: &CharResolver| -> Vec<Vec<CharCodeStyle>> {
+            resolver
+                .get_lines()
+                .iter()
+                .map(|line| line.char_codes().iter().map(|cc| cc.style).collect())
+                .collect()
+        };
+        assert_eq!(styles(&copied_lines), styles(&resolver));
+        assert_eq!(text(lines, &selection, false), "abc d\ne");
+    }
+
+    #[test]
+    fn click_counter_cycles_units() {
+        let mut counter = ClickCounter::new();
+        assert_eq!(counter.click((0, 1)), SelectionUnit::Char);
+        assert_eq!(counter.click((0, 1)), SelectionUnit::Word);
+        assert_eq!(counter.click((0, 1)), SelectionUnit::Line);
+        assert_eq!(counter.click((0, 1)), SelectionUnit::Char);
+        // 点击其他位置重新计数
+        assert_eq!(counter.click((0, 1)), SelectionUnit::Word);
+        assert_eq!(counter.click((0, 2)), SelectionUnit::Char);
+    }
+}
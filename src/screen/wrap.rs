@@ -0,0 +1,268 @@
+use crate::constants::{CHAR_CELL_THIN_WIDTH, CHAR_CELL_WIDE_WIDTH};
+use crate::screen::char_line::{CharCode, CharLine};
+use crate::utils::is_wide_char;
+
+/// 字符在屏幕上的显示宽度，宽体字符用更宽的格子呈现
+pub fn char_width(c: char) -> f32 {
+    if is_wide_char(c) {
+        CHAR_CELL_WIDE_WIDTH
+    } else {
+        CHAR_CELL_THIN_WIDTH
+    }
+}
+
+/// 屏幕上的一行，即某个字符行自动换行后的一段
+pub struct ScreenRow<'a> {
+    /// 所属的字符行的下标
+    pub line_idx: usize,
+    /// 第一个字符在字符行中的下标
+    pub start: usize,
+    pub char_codes: &'a [CharCode],
+    /// 是否为自动换行产生的后续行
+    pub continuation: bool,
+}
+
+/// 屏幕行的位置：所属字符行的下标，以及它是该字符行换行后的第几个屏幕行（从0开始）
+pub type RowPos = (usize, usize);
+
+/// 按照屏幕的宽度对字符行自动换行，宽体与非宽体字符按各自的显示宽度计算
+pub struct Wrapper {
+    width: f32,
+    /// 后续行的缩进宽度
+    indent: f32,
+}
+
+impl Wrapper {
+    pub fn new(width: f32, indent: f32) -> Self {
+        Self {
+            width,
+            // 缩进最多占屏幕宽度的一半，以免后续行过窄
+            indent: indent.clamp(0., width / 2.),
+        }
+    }
+
+    pub fn indent(&self) -> f32 {
+        self.indent
+    }
+
+    /// 将一个字符行拆分为屏幕行，空行同样占据一个屏幕行。
+    /// 每个屏幕行至少包含一个字符，即使屏幕窄于该字符
+    pub fn wrap<'a>(&self, line_idx: usize, line: &'a CharLine) -> Vec<ScreenRow<'a>> {
+        let char_codes = line.char_codes().as_slice();
+        let mut rows = Vec::new();
+        let mut start = 0;
+        let mut row_width = 0f32;
+        let mut max_width = self.width;
+        for (idx, cc) in char_codes.iter().enumerate() {
+            let width = char_width(cc.c);
+            if row_width + width > max_width && idx > start {
+                rows.push(ScreenRow {
+                    line_idx,
+                    start,
+                    char_codes: &char_codes[start..idx],
+                    continuation: !rows.is_empty(),
+                });
+                start = idx;
+                row_width = 0.;
+                max_width = self.width - self.indent;
+            }
+            row_width += width;
+        }
+        rows.push(ScreenRow {
+            line_idx,
+            start,
+            char_codes: &char_codes[start..],
+            continuation: !rows.is_empty(),
+        });
+        rows
+    }
+
+    /// 从start处的屏幕行开始向下排列，最多count个屏幕行
+    pub fn rows_from<'a>(
+        &self,
+        lines: &'a [CharLine],
+        (start_line, start_row): RowPos,
+        count: usize,
+    ) -> Vec<ScreenRow<'a>> {
+        let mut rows = Vec::new();
+        for (line_idx, line) in lines.iter().enumerate().skip(start_line) {
+            if rows.len() >= count {
+                break;
+            }
+            let skip = if line_idx == start_line { start_row } else { 0 };
+            rows.extend(self.wrap(line_idx, line).into_iter().skip(skip));
+        }
+        rows.truncate(count);
+        rows
+    }
+
+    /// 以最后一个字符行结尾向上排列count个屏幕行时，最上方屏幕行的位置
+    pub fn top_of_end(&self, lines: &[CharLine], count: usize) -> RowPos {
+        let mut remaining = count.max(1);
+        for (line_idx, line) in lines.iter().enumerate().rev() {
+            let rows = self.wrap(line_idx, line).len();
+            if rows >= remaining {
+                return (line_idx, rows - remaining);
+            }
+            remaining -= rows;
+        }
+        (0, 0)
+    }
+
+    /// 从pos处的屏幕行向下（rows为正）或向上移动rows个屏幕行，不会越过第一个与最后一个屏幕行。
+    /// 重新换行后pos可能超出字符行的屏幕行数，此时从该字符行的最后一个屏幕行开始移动
+    pub fn offset(&self, lines: &[CharLine], (line_idx, row): RowPos, rows: isize) -> RowPos {
+        let Some(last) = lines.len().checked_sub(1) else {
+            return (0, 0);
+        };
+        let row_count = |line_idx: usize| self.wrap(line_idx, &lines[line_idx]).len();
+        let mut line_idx = line_idx.min(last);
+        let mut row = (row.min(row_count(line_idx) - 1) as isize).saturating_add(rows);
+        while row < 0 {
+            if line_idx == 0 {
+                return (0, 0);
+            }
+            line_idx -= 1;
+            row += row_count(line_idx) as isize;
+        }
+        loop {
+            let count = row_count(line_idx);
+            if (row as usize) < count || line_idx == last {
+                return (line_idx, (row as usize).min(count - 1));
+            }
+            row -= count as isize;
+            line_idx += 1;
+        }
+    }
+
+    /// 以最后一个字符行结尾向上排列，最多count个屏幕行，最上方的字符行可能只显示后面的部分
+    pub fn rows_to_end<'a>(&self, lines: &'a [CharLine], count: usize) -> Vec<ScreenRow<'a>> {
+        let mut rows = Vec::new();
+        for (line_idx, line) in lines.iter().enumerate().rev() {
+            if rows.len() >= count {
+                break;
+            }
+            // 逆序收集，最后再整体反转
+            rows.extend(self.wrap(line_idx, line).into_iter().rev());
+        }
+        rows.truncate(count);
+        rows.reverse();
+        rows
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::screen::char_line::CharCodeStyle;
+
+    fn line(text: &str) -> CharLine {
+        let mut line = CharLine::new();
+        for c in text.chars() {
+            line.push_code(CharCode::new(c, CharCodeStyle::new()));
+        }
+        line
+    }
+
+    fn texts(rows: &[ScreenRow]) -> Vec<String> {
+        rows.iter()
+            .map(|row| row.char_codes.iter().map(|cc| cc.c).collect())
+            .collect()
+    }
+
+    /// 能容纳columns个非宽体字符的屏幕宽度
+    fn width(columns: usize) -> f32 {
+        columns as f32 * CHAR_CELL_THIN_WIDTH
+    }
+
+    #[test]
+    fn wraps_by_display_width() {
+        let wrapper = Wrapper::new(width(4), 0.);
+        assert_eq!(texts(&wrapper.wrap(0, &line("abcdefghij"))), ["abcd", "efgh", "ij"]);
+        // 宽体字符比非宽体字符宽，每行只能容纳三个
+        assert_eq!(texts(&wrapper.wrap(0, &line("你好世界啊"))), ["你好世", "界啊"]);
+        assert_eq!(texts(&wrapper.wrap(0, &line("ab你好"))), ["ab你", "好"]);
+    }
+
+    #[test]
+    fn short_and_empty_lines_take_one_row() {
+        let wrapper = Wrapper::new(width(4), 0.);
+        let empty = line("");
+        let rows = wrapper.wrap(3, &empty);
+        assert_eq!(texts(&rows), [""]);
+        assert_eq!(rows[0].line_idx, 3);
+        assert!(!rows[0].continuation);
+        assert_eq!(texts(&wrapper.wrap(0, &line("abcd"))), ["abcd"]);
+    }
+
+    #[test]
+    fn continuation_rows_are_indented() {
+        let wrapper = Wrapper::new(width(4), width(1));
+        let long = line("abcdefghij");
+        let rows = wrapper.wrap(0, &long);
+        assert_eq!(texts(&rows), ["abcd", "efg", "hij"]);
+        let continuation: Vec<bool> = rows.iter().map(|row| row.continuation).collect();
+        assert_eq!(continuation, [false, true, true]);
+        let starts: Vec<usize> = rows.iter().map(|row| row.start).collect();
+        assert_eq!(starts, [0, 4, 7]);
+    }
+
+    #[test]
+    fn narrow_screen_still_makes_progress() {
+        let wrapper = Wrapper::new(CHAR_CELL_THIN_WIDTH / 2., 0.);
+        assert_eq!(texts(&wrapper.wrap(0, &line("a你"))), ["a", "你"]);
+    }
+
+    #[test]
+    fn rows_from_and_to_end() {
+        let wrapper = Wrapper::new(width(4), 0.);
+        let lines = [line("123456"), line("ab"), line("cdefghi")];
+        assert_eq!(
+            texts(&wrapper.rows_from(&lines, (0, 0), 3)),
+            ["1234", "56", "ab"]
+        );
+        assert_eq!(
+            texts(&wrapper.rows_from(&lines, (1, 0), 9)),
+            ["ab", "cdef", "ghi"]
+        );
+        // 从字符行中间的屏幕行开始
+        assert_eq!(texts(&wrapper.rows_from(&lines, (0, 1), 2)), ["56", "ab"]);
+        assert_eq!(texts(&wrapper.rows_from(&lines, (2, 1), 2)), ["ghi"]);
+        let rows = wrapper.rows_to_end(&lines, 4);
+        assert_eq!(texts(&rows), ["56", "ab", "cdef", "ghi"]);
+        // 最上方只显示了后半部分的字符行
+        assert_eq!(rows[0].line_idx, 0);
+        assert!(rows[0].continuation);
+        assert_eq!(texts(&wrapper.rows_to_end(&lines, 9)).len(), 5);
+    }
+
+    #[test]
+    fn top_of_end_counts_wrapped_rows() {
+        let wrapper = Wrapper::new(width(4), 0.);
+        let lines = [line("123456"), line("ab"), line("cdefghi")];
+        assert_eq!(wrapper.top_of_end(&lines, 1), (2, 1));
+        assert_eq!(wrapper.top_of_end(&lines, 2), (2, 0));
+        assert_eq!(wrapper.top_of_end(&lines, 4), (0, 1));
+        assert_eq!(wrapper.top_of_end(&lines, 9), (0, 0));
+        assert_eq!(wrapper.top_of_end(&[], 3), (0, 0));
+    }
+
+    #[test]
+    fn offset_steps_through_wrapped_rows() {
+        let wrapper = Wrapper::new(width(4), 0.);
+        // 依次占据2、1、2个屏幕行
+        let lines = [line("123456"), line("ab"), line("cdefghi")];
+        assert_eq!(wrapper.offset(&lines, (0, 0), 1), (0, 1));
+        assert_eq!(wrapper.offset(&lines, (0, 1), 1), (1, 0));
+        assert_eq!(wrapper.offset(&lines, (0, 0), 4), (2, 1));
+        assert_eq!(wrapper.offset(&lines, (2, 1), -2), (1, 0));
+        assert_eq!(wrapper.offset(&lines, (2, 0), -3), (0, 0));
+        // 不越过第一个与最后一个屏幕行
+        assert_eq!(wrapper.offset(&lines, (1, 0), isize::MAX), (2, 1));
+        assert_eq!(wrapper.offset(&lines, (1, 0), -isize::MAX), (0, 0));
+        // 超出范围的位置先移到最近的屏幕行
+        assert_eq!(wrapper.offset(&lines, (1, 5), 0), (1, 0));
+        assert_eq!(wrapper.offset(&lines, (9, 9), 0), (2, 1));
+        assert_eq!(wrapper.offset(&[], (1, 1), 1), (0, 0));
+    }
+}
@@ -0,0 +1,372 @@
+use ggez::event::MouseButton;
+use ggez::graphics::{Canvas, Rect};
+use ggez::input::keyboard::{KeyInput, KeyMods};
+use ggez::mint::Point2;
+use ggez::winit::event::VirtualKeyCode;
+use ggez::{Context, GameResult};
+
+use crate::command::ClientCommand;
+use crate::config::{Config, ConnectionProfile, Theme};
+use crate::net::options::gmcp;
+use crate::net::reconnect::Reconnect;
+use crate::net::worker::ConnectionHandle;
+use crate::net::{ConnectionEvent, ConnectionState};
+use crate::oob::OobStore;
+use crate::screen::Screen;
+use crate::ui::text_input::TextInput;
+
+/// 鼠标滚轮每滚动一格，屏幕滚动的屏幕行数
+const WHEEL_SCROLL_ROWS: f32 = 3.;
+
+/// 一个独立的会话，拥有各自的连接、屏幕、输入框及其历史记录，
+/// 以及自动登录、自动重连等自动化状态
+pub struct Session {
+    /// 当前使用的连接配置，尚未选择时为None，此时展示连接选择界面
+    profile: Option<ConnectionProfile>,
+    connection_state: ConnectionState,
+    reconnect: Reconnect,
+    /// 服务器通过GMCP等协议发送的最新数据
+    oob_store: OobStore,
+    screen: Screen,
+    text_input: TextInput,
+    /// 会话在后台时是否收到了新的输出，用于在标签上提示
+    activity: bool,
+}
+
+impl Session {
+    pub fn new(config: &Config, bold_font: bool, screen_bounds: Rect, input_bounds: Rect) -> Self {
+        Self {
+            profile: None,
+            connection_state: ConnectionState::Disconnected,
+            reconnect: Reconnect::new(),
+            oob_store: OobStore::new(),
+            screen: Screen::new(screen_bounds, &config.screen, bold_font),
+            text_input: TextInput::new("hello, world.你好，世界。".into(), input_bounds),
+            activity: false,
+        }
+    }
+
+    pub fn profile(&self) -> Option<&ConnectionProfile> {
+        self.profile.as_ref()
+    }
+
+    /// 标签上展示的会话名称
+    pub fn title(&self) -> &str {
+        self.profile.as_ref().map_or("新会话", |p| p.name.as_str())
+    }
+
+    pub fn has_activity(&self) -> bool {
+        self.activity
+    }
+
+    pub fn clear_activity(&mut self) {
+        self.activity = false;
+    }
+
+    /// 取出自上次调用以来服务器是否发送过响铃字符
+    pub fn take_bell(&mut self) -> bool {
+        self.screen.take_bell()
+    }
+
+    /// 按照连接配置发起连接，连接在网络线程中建立，结果通过连接事件返回
+    pub fn connect(&mut self, profile: ConnectionProfile) {
+        self.disconnect_silently();
+        let via = match &profile.proxy {
+            Some(proxy) => format!("，经由代理 {}", proxy.address()),
+            None => String::new(),
+        };
+        self.screen.print_info(&format!(
+            "正在连接 {}（{}{}）……",
+            profile.name,
+            profile.address(),
+            via
+        ));
+        self.oob_store.clear();
+        let handle = ConnectionHandle::open(profile.clone(), self.screen.window_size());
+        self.profile = Some(profile);
+        match handle {
+            Ok(handle) => self.connection_state = ConnectionState::Connecting(handle),
+            Err(err) => self.on_connection_failed(&err.to_string()),
+        }
+    }
+
+    /// 执行到期的自动重连，并应用网络线程处理好的事件。
+    /// background为true表示会话不在当前标签，此时收到的输出会被记为未读
+    pub fn update(&mut self, background: bool) {
+        if self.reconnect.take_due() {
+            if let Some(profile) = self.profile.clone() {
+                self.connect(profile);
+            }
+        }
+        let events = self
+            .connection_state
+            .handle()
+            .map(ConnectionHandle::poll)
+            .unwrap_or_default();
+        for event in events {
+            if background && matches!(event, ConnectionEvent::Text(_)) {
+                self.activity = true;
+            }
+            self.on_connection_event(event);
+        }
+    }
+
+    fn on_connected(&mut self) {
+        let ConnectionState::Connecting(handle) =
+            std::mem::replace(&mut self.connection_state, ConnectionState::Disconnected)
+        else {
+            return;
+        };
+        self.connection_state = ConnectionState::Connected(handle);
+        self.reconnect.reset();
+        let Some(profile) = self.profile.as_ref() else {
+            return;
+        };
+        self.screen.print_info(&format!("已连接到 {}", profile.address()));
+        for command in profile.auto_login_commands() {
+            self.send_line(&command);
+        }
+    }
+
+    fn on_connection_failed(&mut self, reason: &str) {
+        let address = self.profile.as_ref().map(|p| p.address()).unwrap_or_default();
+        self.connection_state = ConnectionState::Failed;
+        self.on_connection_lost(&format!("连接 {} 失败：{}", address, reason));
+    }
+
+    fn on_connection_event(&mut self, event: ConnectionEvent) {
+        match event {
+            ConnectionEvent::Connected => self.on_connected(),
+            ConnectionEvent::Failed(reason) => self.on_connection_failed(&reason),
+            ConnectionEvent::Text(text) => self.screen.load_text(&text),
+            ConnectionEvent::Error(message) => self.screen.print_error(&message),
+            ConnectionEvent::Prompt => self.screen.mark_prompt(),
+            ConnectionEvent::ServerEcho(enabled) => self.text_input.set_masked(enabled),
+            ConnectionEvent::Gmcp(package, data) if gmcp::is_delta_package(&package) => {
+                self.oob_store.merge(&package, data)
+            }
+            ConnectionEvent::Gmcp(package, data) => self.oob_store.update(&package, data),
+            // MSDP通过REPORT只上报发生变化的数据，与原有数据合并
+            ConnectionEvent::Msdp(name, value) => {
+                self.oob_store.merge(&format!("MSDP.{}", name), value)
+            }
+            ConnectionEvent::Closed(reason) => {
+                self.on_connection_lost(&format!("连接已断开：{}", reason))
+            }
+        }
+    }
+
+    /// 连接建立失败或意外断开，输出原因，并按配置安排自动重连
+    fn on_connection_lost(&mut self, message: &str) {
+        if self.connection_state.handle().is_some() {
+            self.connection_state = ConnectionState::Disconnected;
+        }
+        self.text_input.set_masked(false);
+        self.screen.print_error(message);
+        let auto_reconnect = self.profile.as_ref().is_some_and(|p| p.auto_reconnect);
+        if auto_reconnect {
+            let delay = self.reconnect.schedule();
+            self.screen
+                .print_info(&format!("{}秒后自动重新连接……", delay.as_secs()));
+        } else {
+            self.screen.print_info("输入 #connect 重新连接");
+        }
+    }
+
+    /// 主动断开连接，并取消尚未执行的自动重连
+    fn disconnect(&mut self) {
+        if self.disconnect_silently() {
+            self.screen.print_info("已断开连接");
+        }
+    }
+
+    /// 断开连接但不输出提示，返回此前是否处于连接中
+    fn disconnect_silently(&mut self) -> bool {
+        self.reconnect.cancel();
+        let connected = self.connection_state.handle().is_some();
+        self.connection_state = ConnectionState::Disconnected;
+        self.text_input.set_masked(false);
+        connected
+    }
+
+    /// 向服务器发送一行命令，发送失败时网络线程会报告连接断开
+    fn send_line(&mut self, line: &str) {
+        match self.connection_state.connection() {
+            Some(connection) => connection.send_line(line),
+            None => self.screen.print_error("尚未连接到服务器"),
+        }
+    }
+
+    /// 执行客户端命令，涉及所有会话的全局命令原样返回，由调用方处理
+    fn execute_client_command(
+        &mut self,
+        command: ClientCommand,
+        config: &Config,
+    ) -> Option<ClientCommand> {
+        match command {
+            ClientCommand::Connect(None) => match self.profile.clone() {
+                Some(profile) => self.connect(profile),
+                None => self.screen.print_error("没有可以重新连接的配置"),
+            },
+            ClientCommand::Connect(Some(name)) => match config.find_profile(&name) {
+                Some(profile) => self.connect(profile.clone()),
+                None => self
+                    .screen
+                    .print_error(&format!("配置文件中不存在名为 {} 的连接配置", name)),
+            },
+            ClientCommand::Disconnect => self.disconnect(),
+            ClientCommand::Oob(path) => self.print_oob(&path),
+            ClientCommand::Msdp(command, values) => self.send_msdp(&command, &values),
+            ClientCommand::Theme(_) => return Some(command),
+        }
+        None
+    }
+
+    fn send_msdp(&mut self, command: &str, values: &[String]) {
+        match self.connection_state.connection() {
+            Some(connection) => connection.send_msdp(command, values),
+            None => self.screen.print_error("尚未连接到服务器"),
+        }
+    }
+
+    fn print_oob(&mut self, path: &str) {
+        let Some(value) = self.oob_store.get(path) else {
+            self.screen.print_error(&format!("没有路径为 {} 的数据", path));
+            return;
+        };
+        let text = serde_json::to_string_pretty(value).unwrap_or_default();
+        for line in text.lines() {
+            self.screen.print_info(line);
+        }
+    }
+
+    /// 处理输入框提交的一行内容，返回需要由调用方处理的全局命令
+    fn submit_line(&mut self, line: String, config: &Config) -> Option<ClientCommand> {
+        if self.text_input.masked() {
+            // 服务器负责回显时（如输入密码），不做本地回显，也不解析客户端命令
+            self.send_line(&line);
+            return None;
+        }
+        // 本地回显提交的文本，并追加回车内容，回滚查看时回到底部
+        self.screen.scroll_to_bottom();
+        self.screen.echo_input(&line);
+        match ClientCommand::parse(&line) {
+            Some(Ok(command)) => return self.execute_client_command(command, config),
+            Some(Err(err)) => self.screen.print_error(&err),
+            None => self.send_line(&line),
+        }
+        None
+    }
+
+    /// 在屏幕上输出一行客户端提示信息
+    pub fn print_info(&mut self, text: &str) {
+        self.screen.print_info(text);
+    }
+
+    /// 在屏幕上输出一行客户端错误信息
+    pub fn print_error(&mut self, text: &str) {
+        self.screen.print_error(text);
+    }
+
+    pub fn draw(&mut self, canvas: &mut Canvas, ctx: &mut Context, theme: &Theme) -> GameResult {
+        self.screen.draw(canvas, ctx, theme);
+        self.text_input.draw(canvas, ctx, theme)
+    }
+
+    pub fn mouse_button_down_event(&mut self, button: MouseButton, x: f32, y: f32) {
+        self.text_input
+            .set_focused(self.text_input.bounds().contains(Point2::from([x, y])));
+        if button == MouseButton::Left {
+            self.screen.mouse_down(x, y);
+        }
+    }
+
+    pub fn mouse_button_up_event(&mut self, button: MouseButton) {
+        if button == MouseButton::Left {
+            self.screen.mouse_up();
+        }
+    }
+
+    /// 拖动鼠标选择屏幕上的文本
+    pub fn mouse_motion_event(&mut self, x: f32, y: f32) {
+        self.screen.mouse_move(x, y);
+    }
+
+    /// 屏幕上选中的文本，ansi为true时保留颜色等样式
+    pub fn selected_text(&self, ansi: bool) -> Option<String> {
+        self.screen.selected_text(ansi)
+    }
+
+    /// 鼠标滚轮滚动屏幕，y为正时向上滚动
+    pub fn mouse_wheel_event(&mut self, y: f32) {
+        self.screen.scroll((y * WHEEL_SCROLL_ROWS).round() as isize);
+    }
+
+    /// 处理回滚查看的按键，返回按键是否已被处理
+    fn scroll_key_down_event(&mut self, code: VirtualKeyCode, mods: KeyMods) -> bool {
+        match code {
+            VirtualKeyCode::PageUp => self.screen.scroll(self.screen.page_rows()),
+            VirtualKeyCode::PageDown => self.screen.scroll(-self.screen.page_rows()),
+            VirtualKeyCode::Home if mods.contains(KeyMods::SHIFT) => {
+                self.screen.scroll(isize::MAX)
+            }
+            VirtualKeyCode::End if mods.contains(KeyMods::SHIFT) => {
+                self.screen.scroll_to_bottom()
+            }
+            _ => return false,
+        }
+        true
+    }
+
+    /// 处理屏幕与输入框的按键，提交的内容中若有全局命令则返回，由调用方处理
+    pub fn key_down_event(&mut self, input: KeyInput, config: &Config) -> Option<ClientCommand> {
+        let KeyInput {
+            keycode: Some(code),
+            mods,
+            ..
+        } = input
+        else {
+            return None;
+        };
+        // 输入框没有焦点时也可以回滚查看
+        if self.scroll_key_down_event(code, mods) || !self.text_input.focused() {
+            return None;
+        }
+        match code {
+            VirtualKeyCode::Escape => self.text_input.set_focused(false),
+            VirtualKeyCode::Delete | VirtualKeyCode::Back => {
+                if KeyMods::CTRL == mods {
+                    self.text_input.delete_char(5)
+                } else {
+                    self.text_input.delete_char(1)
+                }
+            }
+            VirtualKeyCode::Return | VirtualKeyCode::Caret => {
+                if let Some(txt_str) = self.text_input.commit() {
+                    return self.submit_line(txt_str, config);
+                }
+            }
+            _ => {}
+        }
+        None
+    }
+
+    pub fn text_input_event(&mut self, character: char) {
+        if self.text_input.focused() && self.profile.is_some() {
+            // 控制键在key_down_event中处理，这里仅处理提交内容
+            if !character.is_control() {
+                // 支持中文等非ascii字符，发送时再按照服务器编码进行编码
+                self.text_input.append_char(character);
+            }
+        }
+    }
+
+    /// 窗口大小变化时更新屏幕与输入框的区域，并告知服务器新的窗口大小
+    pub fn update_bounds(&mut self, screen_bounds: Rect, input_bounds: Rect) {
+        self.screen.update_bounds(screen_bounds);
+        self.text_input.update_bounds(input_bounds);
+        if let Some(handle) = self.connection_state.handle() {
+            handle.set_window_size(self.screen.window_size());
+        }
+    }
+}
@@ -0,0 +1,101 @@
+use std::f32::consts::PI;
+use std::time::{Duration, Instant};
+
+use ggez::audio::{SoundData, SoundSource, Source};
+use ggez::graphics::{Canvas, Color, DrawParam, Quad, Rect};
+use ggez::winit::window::UserAttentionType;
+use ggez::Context;
+
+use crate::config::BellMode;
+
+/// 屏幕闪烁的持续时间
+const FLASH_DURATION: Duration = Duration::from_millis(120);
+/// 提示音的采样率、频率与时长
+const SAMPLE_RATE: u32 = 22050;
+const TONE_FREQUENCY: f32 = 880.;
+const TONE_DURATION: f32 = 0.15;
+
+/// 按配置的方式响应服务器的响铃字符
+pub struct Bell {
+    mode: BellMode,
+    /// 屏幕闪烁结束的时刻
+    flash_until: Option<Instant>,
+    /// 提示音在第一次响铃时生成
+    sound: Option<Source>,
+}
+
+impl Bell {
+    pub fn new(mode: BellMode) -> Self {
+        Self {
+            mode,
+            flash_until: None,
+            sound: None,
+        }
+    }
+
+    pub fn ring(&mut self, ctx: &Context) {
+        match self.mode {
+            BellMode::None => {}
+            BellMode::Visual => self.flash_until = Some(Instant::now() + FLASH_DURATION),
+            BellMode::Sound => {
+                // 没有可用的音频设备时静默忽略
+                let _ = self.play_sound(ctx);
+            }
+            BellMode::Attention => ctx
+                .gfx
+                .window()
+                .request_user_attention(Some(UserAttentionType::Informational)),
+        }
+    }
+
+    fn play_sound(&mut self, ctx: &Context) -> ggez::GameResult {
+        if self.sound.is_none() {
+            let data = SoundData::from_bytes(&tone_wav());
+            self.sound = Some(Source::from_data(ctx, data)?);
+        }
+        match self.sound.as_mut() {
+            Some(sound) => sound.play_detached(ctx),
+            None => Ok(()),
+        }
+    }
+
+    /// 屏幕闪烁期间在bounds上绘制一层半透明的白色
+    pub fn draw(&self, canvas: &mut Canvas, bounds: Rect) {
+        if self.flash_until.is_some_and(|until| Instant::now() < until) {
+            canvas.draw(
+                &Quad,
+                DrawParam::default()
+                    .dest(bounds.point())
+                    .scale(bounds.size())
+                    .color(Color::new(1., 1., 1., 0.25)),
+            );
+        }
+    }
+}
+
+/// 生成一段单声道16位PCM的WAV格式正弦波提示音，渐弱结束以免出现爆音
+fn tone_wav() -> Vec<u8> {
+    let sample_count = (SAMPLE_RATE as f32 * TONE_DURATION) as u32;
+    let data_size = sample_count * 2;
+    let mut wav = Vec::with_capacity(44 + data_size as usize);
+    wav.extend(b"RIFF");
+    wav.extend((36 + data_size).to_le_bytes());
+    wav.extend(b"WAVEfmt ");
+    wav.extend(16u32.to_le_bytes());
+    // PCM格式，单声道
+    wav.extend(1u16.to_le_bytes());
+    wav.extend(1u16.to_le_bytes());
+    wav.extend(SAMPLE_RATE.to_le_bytes());
+    wav.extend((SAMPLE_RATE * 2).to_le_bytes());
+    wav.extend(2u16.to_le_bytes());
+    wav.extend(16u16.to_le_bytes());
+    wav.extend(b"data");
+    wav.extend(data_size.to_le_bytes());
+    for idx in 0..sample_count {
+        let t = idx as f32 / SAMPLE_RATE as f32;
+        let fade = 1. - idx as f32 / sample_count as f32;
+        let sample = (2. * PI * TONE_FREQUENCY * t).sin() * fade * 0.3;
+        wav.extend(((sample * i16::MAX as f32) as i16).to_le_bytes());
+    }
+    wav
+}
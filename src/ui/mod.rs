@@ -1 +1,4 @@
-pub mod text_input;
\ No newline at end of file
+pub mod bell;
+pub mod profile_picker;
+pub mod tab_bar;
+pub mod text_input;
@@ -0,0 +1,103 @@
+use ggez::graphics::{Canvas, Color, DrawParam, PxScale, Quad, Rect, Text, TextFragment};
+use ggez::mint::Point2;
+
+use crate::config::ConnectionProfile;
+use crate::constants::{CHAR_CELL_HEIGHT, FONT_FLAG_NAME};
+
+/// 选择界面中每一行的高度
+const ROW_HEIGHT: f32 = CHAR_CELL_HEIGHT + 12.;
+
+/// 连接前展示的配置选择界面，使用上下方向键或鼠标选择，回车连接
+pub struct ProfilePicker {
+    profiles: Vec<ConnectionProfile>,
+    selected: usize,
+    bounds: Rect,
+    padding: f32,
+}
+
+impl ProfilePicker {
+    pub fn new(profiles: Vec<ConnectionProfile>, bounds: Rect) -> Self {
+        Self {
+            profiles,
+            selected: 0,
+            bounds,
+            padding: 20.,
+        }
+    }
+
+    pub fn draw(&self, canvas: &mut Canvas) {
+        self.draw_text(canvas, "请选择要连接的服务器（↑↓选择，回车连接）", 0, Color::WHITE);
+        if self.profiles.is_empty() {
+            self.draw_text(canvas, "配置文件中没有任何连接配置", 1, Color::from_rgb(100, 100, 100));
+        }
+        for (idx, profile) in self.profiles.iter().enumerate() {
+            let row_idx = idx + 1;
+            let color = if idx == self.selected {
+                canvas.draw(
+                    &Quad,
+                    DrawParam::default()
+                        .dest(self.row_rect(row_idx).point())
+                        .scale(self.row_rect(row_idx).size())
+                        .color(Color::from_rgb(40, 40, 80)),
+                );
+                Color::WHITE
+            } else {
+                Color::from_rgb(160, 160, 160)
+            };
+            let line = format!("{}  ({}, {})", profile.name, profile.address(), profile.encoding);
+            self.draw_text(canvas, &line, row_idx, color);
+        }
+    }
+
+    fn draw_text(&self, canvas: &mut Canvas, text: &str, row_idx: usize, color: Color) {
+        let rect = self.row_rect(row_idx);
+        let txt = Text::new(TextFragment {
+            text: text.into(),
+            font: Some(FONT_FLAG_NAME.into()),
+            scale: Some(PxScale::from(CHAR_CELL_HEIGHT)),
+            color: Some(color),
+        });
+        canvas.draw(
+            &txt,
+            DrawParam::default().dest(Point2::from([rect.x + self.padding, rect.y + 6.])),
+        );
+    }
+
+    /// 第row_idx行（标题为第0行）在界面上的区域
+    fn row_rect(&self, row_idx: usize) -> Rect {
+        Rect::new(
+            self.bounds.x,
+            self.bounds.y + self.padding + row_idx as f32 * ROW_HEIGHT,
+            self.bounds.w,
+            ROW_HEIGHT,
+        )
+    }
+
+    pub fn update_bounds(&mut self, bounds: Rect) {
+        self.bounds = bounds;
+    }
+
+    pub fn select_prev(&mut self) {
+        self.selected = self.selected.saturating_sub(1);
+    }
+
+    pub fn select_next(&mut self) {
+        if self.selected + 1 < self.profiles.len() {
+            self.selected += 1;
+        }
+    }
+
+    /// 选中鼠标位置所在的配置，返回是否命中了某一项
+    pub fn select_at(&mut self, x: f32, y: f32) -> bool {
+        let hit = (0..self.profiles.len())
+            .find(|idx| self.row_rect(idx + 1).contains(Point2::from([x, y])));
+        if let Some(idx) = hit {
+            self.selected = idx;
+        }
+        hit.is_some()
+    }
+
+    pub fn selected_profile(&self) -> Option<&ConnectionProfile> {
+        self.profiles.get(self.selected)
+    }
+}
@@ -0,0 +1,145 @@
+use ggez::graphics::{Canvas, Color, DrawParam, PxScale, Quad, Rect, Text, TextFragment};
+use ggez::mint::Point2;
+
+use crate::constants::{CHAR_CELL_HEIGHT, FONT_FLAG_NAME};
+
+/// 标签栏的高度
+pub const TAB_BAR_HEIGHT: f32 = CHAR_CELL_HEIGHT + 8.;
+/// 标签的最大宽度，标签较多时由各标签等分标签栏
+const MAX_TAB_WIDTH: f32 = 200.;
+/// 标签的最小宽度，标签栏容纳不下时整体滚动，保证当前标签可见
+const MIN_TAB_WIDTH: f32 = 80.;
+/// 标签文字的字号，略小于终端字符
+const TAB_FONT_SIZE: f32 = CHAR_CELL_HEIGHT - 6.;
+
+/// 标签栏中一个会话的展示信息
+pub struct Tab<'a> {
+    pub title: &'a str,
+    /// 后台会话是否有未读的输出
+    pub activity: bool,
+}
+
+/// 窗口顶部的会话标签栏，Ctrl+T新建、Ctrl+W关闭、Ctrl+Tab切换、Ctrl+数字直接选择
+pub struct TabBar {
+    bounds: Rect,
+}
+
+impl TabBar {
+    pub fn new(bounds: Rect) -> Self {
+        Self { bounds }
+    }
+
+    pub fn draw(&self, canvas: &mut Canvas, tabs: &[Tab], active: usize) {
+        canvas.draw(
+            &Quad,
+            DrawParam::default()
+                .dest(self.bounds.point())
+                .scale(self.bounds.size())
+                .color(Color::from_rgb(20, 20, 20)),
+        );
+        for (idx, tab) in tabs.iter().enumerate() {
+            let rect = self.tab_rect(idx, tabs.len(), active);
+            if rect.x < self.bounds.x || rect.right() > self.bounds.right() {
+                continue;
+            }
+            let (background, color) = if idx == active {
+                (Color::from_rgb(40, 40, 80), Color::WHITE)
+            } else if tab.activity {
+                (Color::from_rgb(30, 30, 30), Color::YELLOW)
+            } else {
+                (Color::from_rgb(30, 30, 30), Color::from_rgb(160, 160, 160))
+            };
+            // 标签之间留出1像素的间隔
+            canvas.draw(
+                &Quad,
+                DrawParam::default()
+                    .dest(rect.point())
+                    .scale([rect.w - 1., rect.h])
+                    .color(background),
+            );
+            // 有未读输出的后台会话在名称前加上标记
+            let marker = if tab.activity && idx != active { "● " } else { "" };
+            let mut txt = Text::new(TextFragment {
+                text: format!("{}{} {}", marker, idx + 1, tab.title),
+                font: Some(FONT_FLAG_NAME.into()),
+                scale: Some(PxScale::from(TAB_FONT_SIZE)),
+                color: Some(color),
+            });
+            txt.set_bounds([rect.w - 16., rect.h]);
+            canvas.draw(
+                &txt,
+                DrawParam::default().dest(Point2::from([
+                    rect.x + 8.,
+                    rect.y + (rect.h - TAB_FONT_SIZE) / 2.,
+                ])),
+            );
+        }
+    }
+
+    /// 鼠标位置所在的标签
+    pub fn tab_at(&self, x: f32, y: f32, count: usize, active: usize) -> Option<usize> {
+        (0..count).find(|idx| {
+            let rect = self.tab_rect(*idx, count, active);
+            rect.x >= self.bounds.x
+                && rect.right() <= self.bounds.right()
+                && rect.contains(Point2::from([x, y]))
+        })
+    }
+
+    fn tab_width(&self, count: usize) -> f32 {
+        (self.bounds.w / count.max(1) as f32).clamp(MIN_TAB_WIDTH, MAX_TAB_WIDTH)
+    }
+
+    /// 第idx个标签的位置。标签栏容纳不下所有标签时，向左滚动整数个标签，
+    /// 使当前标签完整显示，放不下的标签不显示
+    fn tab_rect(&self, idx: usize, count: usize, active: usize) -> Rect {
+        let width = self.tab_width(count);
+        let visible = ((self.bounds.w / width) as usize).max(1);
+        let first = (active + 1).saturating_sub(visible);
+        Rect::new(
+            self.bounds.x + (idx as f32 - first as f32) * width,
+            self.bounds.y,
+            width,
+            self.bounds.h,
+        )
+    }
+
+    pub fn update_bounds(&mut self, bounds: Rect) {
+        self.bounds = bounds;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tab_bar(width: f32) -> TabBar {
+        TabBar::new(Rect::new(0., 0., width, TAB_BAR_HEIGHT))
+    }
+
+    #[test]
+    fn tabs_shrink_to_fit() {
+        let bar = tab_bar(1000.);
+        assert_eq!(bar.tab_rect(1, 3, 0).x, MAX_TAB_WIDTH);
+        assert_eq!(bar.tab_rect(1, 3, 0).w, MAX_TAB_WIDTH);
+        // 8个标签等分标签栏
+        assert_eq!(bar.tab_rect(7, 8, 0).w, 125.);
+        assert_eq!(bar.tab_rect(7, 8, 0).right(), 1000.);
+        assert_eq!(bar.tab_at(990., 10., 8, 0), Some(7));
+        assert_eq!(bar.tab_at(10., 10., 8, 0), Some(0));
+    }
+
+    #[test]
+    fn overflowing_tabs_scroll_to_active() {
+        // 最多显示5个80像素宽的标签
+        let bar = tab_bar(400.);
+        assert_eq!(bar.tab_rect(0, 20, 2).x, 0.);
+        assert_eq!(bar.tab_at(390., 10., 20, 2), Some(4));
+        // 当前标签为最后一个时显示最后5个
+        assert_eq!(bar.tab_rect(19, 20, 19).right(), 400.);
+        assert_eq!(bar.tab_at(10., 10., 20, 19), Some(15));
+        assert_eq!(bar.tab_at(390., 10., 20, 19), Some(19));
+        // 不在标签栏中显示的标签无法点击
+        assert_eq!(bar.tab_at(450., 10., 20, 2), None);
+    }
+}
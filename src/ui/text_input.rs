@@ -2,17 +2,18 @@ use ggez::graphics::{
     Canvas, Color, DrawMode, DrawParam, FillOptions, Mesh, MeshBuilder, PxScale, Rect,
     StrokeOptions, Text, TextFragment, TextLayout,
 };
-use ggez::mint::Point2;
 use ggez::{Context, GameResult};
 
+use crate::config::Theme;
 use crate::constants::FONT_FLAG_NAME;
 use crate::utils::is_wide_char;
 
 pub struct TextInput {
     focused: bool,
+    /// 是否隐藏输入内容，用于输入密码
+    masked: bool,
     value: String,
     history: Vec<String>,
-    cursor: u16,
     bounds: Rect,
     padding: f32,
 }
@@ -21,38 +22,43 @@ impl TextInput {
     pub fn new(default_value: String, bounds: Rect) -> Self {
         Self {
             focused: true,
+            masked: false,
             value: default_value,
             history: Vec::new(),
-            cursor: 0,
             bounds,
             padding: 5.,
         }
     }
 
-    pub fn draw(&mut self, canvas: &mut Canvas, ctx: &mut Context) -> GameResult<()> {
+    pub fn draw(
+        &mut self,
+        canvas: &mut Canvas,
+        ctx: &mut Context,
+        theme: &Theme,
+    ) -> GameResult<()> {
         // 1. 控件边框
         let mut builder = MeshBuilder::new();
         let mesh_data = builder
             .rectangle(
                 DrawMode::Fill(FillOptions::default()),
                 self.bounds,
-                Color::BLACK,
+                theme.input_background.into(),
             )?
             .rounded_rectangle(
                 DrawMode::Stroke(StrokeOptions::default()),
                 self.bounds,
                 5.0,
-                self.color(),
+                self.color(theme),
             )?
             .build();
         let mesh = Mesh::from_data(ctx, mesh_data);
         canvas.draw(&mesh, DrawParam::default());
         // 2. 绘制文本
-        self.draw_text(canvas);
+        self.draw_text(canvas, theme);
         Ok(())
     }
 
-    fn draw_text(&self, canvas: &mut Canvas) {
+    fn draw_text(&self, canvas: &mut Canvas, theme: &Theme) {
         let char_cell_wide_width = 28.;
         let char_cell_thin_width = 24.;
         let render_bounds = Rect::from([
@@ -61,26 +67,27 @@ impl TextInput {
             self.bounds.w - self.padding * 2.,
             self.bounds.h - self.padding * 2.,
         ]);
+        let display_value = self.display_value();
         // 计算文本宽度，这里使用格子来填充每一个字符
         let mut text_width = 0f32;
         let mut text_overflow: bool = false;
-        for c in self.value.chars() {
+        for c in display_value.chars() {
             let char_width = if is_wide_char(c) {
                 char_cell_wide_width
             } else {
                 char_cell_thin_width
             };
-            text_width = text_width + char_width;
+            text_width += char_width;
             if text_width > render_bounds.w {
                 text_overflow = true;
                 break;
             }
         }
-        let color = self.color();
+        let color = self.color(theme);
         if text_overflow {
             // 反向取字符，从尾巴开始绘制
             let mut next_char_x = render_bounds.x + render_bounds.w;
-            for c in self.value.chars().rev() {
+            for c in display_value.chars().rev() {
                 let char_width = if is_wide_char(c) {
                     char_cell_wide_width
                 } else {
@@ -106,13 +113,13 @@ impl TextInput {
                     .set_layout(TextLayout::center());
                 canvas.draw(
                     &txt,
-                    DrawParam::default().dest(Point2::from(text_rect.center())),
+                    DrawParam::default().dest(text_rect.center()),
                 );
             }
         } else {
             // 正向
             let mut next_char_x = render_bounds.x;
-            for c in self.value.chars() {
+            for c in display_value.chars() {
                 let char_width = if is_wide_char(c) {
                     char_cell_wide_width
                 } else {
@@ -134,18 +141,27 @@ impl TextInput {
                     .set_layout(TextLayout::center());
                 canvas.draw(
                     &txt,
-                    DrawParam::default().dest(Point2::from(text_rect.center())),
+                    DrawParam::default().dest(text_rect.center()),
                 );
                 next_char_x += char_width;
             }
         }
     }
 
-    fn color(&self) -> Color {
+    /// 界面上展示的内容，隐藏输入时每个字符均显示为 *
+    fn display_value(&self) -> String {
+        if self.masked {
+            "*".repeat(self.value.chars().count())
+        } else {
+            self.value.clone()
+        }
+    }
+
+    fn color(&self, theme: &Theme) -> Color {
         if self.focused {
-            Color::WHITE
+            theme.input_foreground.into()
         } else {
-            Color::from_rgb(100, 100, 100)
+            theme.input_inactive.into()
         }
     }
 
@@ -161,6 +177,14 @@ impl TextInput {
         self.focused
     }
 
+    pub fn set_masked(&mut self, masked: bool) {
+        self.masked = masked;
+    }
+
+    pub fn masked(&self) -> bool {
+        self.masked
+    }
+
     pub fn append_char(&mut self, c: char) {
         self.value.push(c);
     }
@@ -176,7 +200,10 @@ impl TextInput {
             None
         } else {
             let curr_val = self.value.clone();
-            self.history.push(curr_val.clone());
+            // 隐藏输入的内容（如密码）不记入历史
+            if !self.masked {
+                self.history.push(curr_val.clone());
+            }
             self.value.clear();
             Some(curr_val.clone())
         }